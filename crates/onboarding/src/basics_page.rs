@@ -339,7 +339,12 @@ fn render_base_keymap_section(tab_index: &mut isize, cx: &mut App) -> impl IntoE
         BaseKeymap::Emacs => Some(5),
         BaseKeymap::Cursor => Some(6),
         BaseKeymap::TextMate => Some(7),
-        BaseKeymap::None => None,
+        BaseKeymap::Vim
+        | BaseKeymap::Helix
+        | BaseKeymap::Nano
+        | BaseKeymap::Kakoune
+        | BaseKeymap::VisualStudio
+        | BaseKeymap::None => None,
     };
 
     return v_flex().gap_2().child(Label::new("Base Keymap")).child(
@@ -1,6 +1,8 @@
+use std::borrow::Cow;
 use std::fmt::{Display, Formatter};
 
 use crate::{self as settings, settings_content::BaseKeymapContent};
+use gpui::AssetSource;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use settings::{RegisterSetting, Settings};
@@ -21,6 +23,11 @@ pub enum BaseKeymap {
     TextMate,
     Emacs,
     Cursor,
+    Vim,
+    Helix,
+    Nano,
+    Kakoune,
+    VisualStudio,
     None,
 }
 
@@ -35,6 +42,11 @@ impl From<BaseKeymapContent> for BaseKeymap {
             BaseKeymapContent::TextMate => Self::TextMate,
             BaseKeymapContent::Emacs => Self::Emacs,
             BaseKeymapContent::Cursor => Self::Cursor,
+            BaseKeymapContent::Vim => Self::Vim,
+            BaseKeymapContent::Helix => Self::Helix,
+            BaseKeymapContent::Nano => Self::Nano,
+            BaseKeymapContent::Kakoune => Self::Kakoune,
+            BaseKeymapContent::VisualStudio => Self::VisualStudio,
             BaseKeymapContent::None => Self::None,
         }
     }
@@ -50,6 +62,11 @@ impl Into<BaseKeymapContent> for BaseKeymap {
             BaseKeymap::TextMate => BaseKeymapContent::TextMate,
             BaseKeymap::Emacs => BaseKeymapContent::Emacs,
             BaseKeymap::Cursor => BaseKeymapContent::Cursor,
+            BaseKeymap::Vim => BaseKeymapContent::Vim,
+            BaseKeymap::Helix => BaseKeymapContent::Helix,
+            BaseKeymap::Nano => BaseKeymapContent::Nano,
+            BaseKeymap::Kakoune => BaseKeymapContent::Kakoune,
+            BaseKeymap::VisualStudio => BaseKeymapContent::VisualStudio,
             BaseKeymap::None => BaseKeymapContent::None,
         }
     }
@@ -57,23 +74,24 @@ impl Into<BaseKeymapContent> for BaseKeymap {
 
 impl Display for BaseKeymap {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            BaseKeymap::Zed => write!(f, "Zed"),
-            BaseKeymap::VSCode => write!(f, "VS Code"),
-            BaseKeymap::JetBrains => write!(f, "JetBrains"),
-            BaseKeymap::SublimeText => write!(f, "Sublime Text"),
-            BaseKeymap::Atom => write!(f, "Atom"),
-            BaseKeymap::TextMate => write!(f, "TextMate"),
-            BaseKeymap::Emacs => write!(f, "Emacs (beta)"),
-            BaseKeymap::Cursor => write!(f, "Cursor (beta)"),
-            BaseKeymap::None => write!(f, "None"),
-        }
+        f.write_str(self.display_name())
     }
 }
 
+/// A base keymap together with what a picker or generated documentation shows
+/// for it, as returned by [`BaseKeymap::catalog`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BaseKeymapEntry {
+    pub base_keymap: BaseKeymap,
+    pub name: &'static str,
+    pub description: &'static str,
+    /// The keymap asset on the current platform, if the base keymap has one.
+    pub asset_path: Option<&'static str>,
+}
+
 impl BaseKeymap {
     #[cfg(target_os = "macos")]
-    pub const OPTIONS: [(&'static str, Self); 8] = [
+    pub const OPTIONS: [(&'static str, Self); 13] = [
         ("Zed (Default)", Self::Zed),
         ("VS Code", Self::VSCode),
         ("Atom", Self::Atom),
@@ -82,10 +100,33 @@ impl BaseKeymap {
         ("Emacs (beta)", Self::Emacs),
         ("TextMate", Self::TextMate),
         ("Cursor", Self::Cursor),
+        ("Vim", Self::Vim),
+        ("Helix", Self::Helix),
+        ("Nano", Self::Nano),
+        ("Kakoune", Self::Kakoune),
+        ("Visual Studio", Self::VisualStudio),
+    ];
+
+    // Visual Studio is listed right after VS Code on Windows, where most of its
+    // users come from.
+    #[cfg(target_os = "windows")]
+    pub const OPTIONS: [(&'static str, Self); 12] = [
+        ("Zed (Default)", Self::Zed),
+        ("VS Code", Self::VSCode),
+        ("Visual Studio", Self::VisualStudio),
+        ("Atom", Self::Atom),
+        ("JetBrains", Self::JetBrains),
+        ("Sublime Text", Self::SublimeText),
+        ("Emacs (beta)", Self::Emacs),
+        ("Cursor", Self::Cursor),
+        ("Vim", Self::Vim),
+        ("Helix", Self::Helix),
+        ("Nano", Self::Nano),
+        ("Kakoune", Self::Kakoune),
     ];
 
-    #[cfg(not(target_os = "macos"))]
-    pub const OPTIONS: [(&'static str, Self); 7] = [
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    pub const OPTIONS: [(&'static str, Self); 12] = [
         ("Zed (Default)", Self::Zed),
         ("VS Code", Self::VSCode),
         ("Atom", Self::Atom),
@@ -93,6 +134,11 @@ impl BaseKeymap {
         ("Sublime Text", Self::SublimeText),
         ("Emacs (beta)", Self::Emacs),
         ("Cursor", Self::Cursor),
+        ("Vim", Self::Vim),
+        ("Helix", Self::Helix),
+        ("Nano", Self::Nano),
+        ("Kakoune", Self::Kakoune),
+        ("Visual Studio", Self::VisualStudio),
     ];
 
     pub fn asset_path(&self) -> Option<&'static str> {
@@ -104,18 +150,79 @@ impl BaseKeymap {
             BaseKeymap::TextMate => Some("keymaps/macos/textmate.json"),
             BaseKeymap::Emacs => Some("keymaps/macos/emacs.json"),
             BaseKeymap::Cursor => Some("keymaps/macos/cursor.json"),
+            BaseKeymap::Vim => Some("keymaps/macos/vim.json"),
+            BaseKeymap::Helix => Some("keymaps/macos/helix.json"),
+            BaseKeymap::Nano => Some("keymaps/macos/nano.json"),
+            BaseKeymap::Kakoune => Some("keymaps/macos/kakoune.json"),
+            BaseKeymap::VisualStudio => Some("keymaps/macos/visual_studio.json"),
             BaseKeymap::VSCode => Some("keymaps/macos/vscode.json"),
             BaseKeymap::Zed => None,
             BaseKeymap::None => None,
         }
 
-        #[cfg(not(target_os = "macos"))]
+        #[cfg(target_os = "windows")]
+        match self {
+            BaseKeymap::JetBrains => Some(windows_asset_path(
+                "keymaps/windows/jetbrains.json",
+                "keymaps/linux/jetbrains.json",
+            )),
+            BaseKeymap::SublimeText => Some(windows_asset_path(
+                "keymaps/windows/sublime_text.json",
+                "keymaps/linux/sublime_text.json",
+            )),
+            BaseKeymap::Atom => Some(windows_asset_path(
+                "keymaps/windows/atom.json",
+                "keymaps/linux/atom.json",
+            )),
+            BaseKeymap::Emacs => Some(windows_asset_path(
+                "keymaps/windows/emacs.json",
+                "keymaps/linux/emacs.json",
+            )),
+            BaseKeymap::Cursor => Some(windows_asset_path(
+                "keymaps/windows/cursor.json",
+                "keymaps/linux/cursor.json",
+            )),
+            BaseKeymap::Vim => Some(windows_asset_path(
+                "keymaps/windows/vim.json",
+                "keymaps/linux/vim.json",
+            )),
+            BaseKeymap::Helix => Some(windows_asset_path(
+                "keymaps/windows/helix.json",
+                "keymaps/linux/helix.json",
+            )),
+            BaseKeymap::Nano => Some(windows_asset_path(
+                "keymaps/windows/nano.json",
+                "keymaps/linux/nano.json",
+            )),
+            BaseKeymap::Kakoune => Some(windows_asset_path(
+                "keymaps/windows/kakoune.json",
+                "keymaps/linux/kakoune.json",
+            )),
+            BaseKeymap::VisualStudio => Some(windows_asset_path(
+                "keymaps/windows/visual_studio.json",
+                "keymaps/linux/visual_studio.json",
+            )),
+            BaseKeymap::TextMate => None,
+            BaseKeymap::VSCode => Some(windows_asset_path(
+                "keymaps/windows/vscode.json",
+                "keymaps/linux/vscode.json",
+            )),
+            BaseKeymap::Zed => None,
+            BaseKeymap::None => None,
+        }
+
+        #[cfg(not(any(target_os = "macos", target_os = "windows")))]
         match self {
             BaseKeymap::JetBrains => Some("keymaps/linux/jetbrains.json"),
             BaseKeymap::SublimeText => Some("keymaps/linux/sublime_text.json"),
             BaseKeymap::Atom => Some("keymaps/linux/atom.json"),
             BaseKeymap::Emacs => Some("keymaps/linux/emacs.json"),
             BaseKeymap::Cursor => Some("keymaps/linux/cursor.json"),
+            BaseKeymap::Vim => Some("keymaps/linux/vim.json"),
+            BaseKeymap::Helix => Some("keymaps/linux/helix.json"),
+            BaseKeymap::Nano => Some("keymaps/linux/nano.json"),
+            BaseKeymap::Kakoune => Some("keymaps/linux/kakoune.json"),
+            BaseKeymap::VisualStudio => Some("keymaps/linux/visual_studio.json"),
             BaseKeymap::TextMate => None,
             BaseKeymap::VSCode => Some("keymaps/linux/vscode.json"),
             BaseKeymap::Zed => None,
@@ -123,16 +230,223 @@ impl BaseKeymap {
         }
     }
 
+    fn display_name(&self) -> &'static str {
+        match self {
+            BaseKeymap::Zed => "Zed",
+            BaseKeymap::VSCode => "VS Code",
+            BaseKeymap::JetBrains => "JetBrains",
+            BaseKeymap::SublimeText => "Sublime Text",
+            BaseKeymap::Atom => "Atom",
+            BaseKeymap::TextMate => "TextMate",
+            BaseKeymap::Emacs => "Emacs (beta)",
+            BaseKeymap::Cursor => "Cursor (beta)",
+            BaseKeymap::Vim => "Vim",
+            BaseKeymap::Helix => "Helix",
+            BaseKeymap::Nano => "Nano",
+            BaseKeymap::Kakoune => "Kakoune",
+            BaseKeymap::VisualStudio => "Visual Studio",
+            BaseKeymap::None => "None",
+        }
+    }
+
+    /// A one line summary of the base keymap, for showing alongside its name.
+    pub fn description(&self) -> &'static str {
+        match self {
+            BaseKeymap::Zed => "Zed's own bindings, designed alongside its features",
+            BaseKeymap::VSCode => "Familiar bindings for VS Code users",
+            BaseKeymap::JetBrains => {
+                "Familiar bindings for IntelliJ IDEA and other JetBrains IDE users"
+            }
+            BaseKeymap::SublimeText => "Familiar bindings for Sublime Text users",
+            BaseKeymap::Atom => "Familiar bindings for Atom users",
+            BaseKeymap::TextMate => "Familiar bindings for TextMate users",
+            BaseKeymap::Emacs => "Emacs style bindings, with chords for cursor movement",
+            BaseKeymap::Cursor => "Familiar bindings for Cursor users",
+            BaseKeymap::Vim => "Vim style bindings for panes and pickers, pair with vim mode",
+            BaseKeymap::Helix => "Helix style bindings for panes and pickers, pair with helix mode",
+            BaseKeymap::Nano => "Familiar control key shortcuts for Nano users",
+            BaseKeymap::Kakoune => {
+                "Kakoune style bindings for panes and pickers, pair with helix mode"
+            }
+            BaseKeymap::VisualStudio => "Familiar bindings for Visual Studio (not VS Code) users",
+            BaseKeymap::None => "No base keybindings; start from scratch",
+        }
+    }
+
+    /// A stable id for the base keymap, for reporting it in telemetry without
+    /// its display name. Ids are never reused or changed, so that reports from
+    /// different releases stay comparable; new base keymaps get the next
+    /// unused id.
+    pub fn telemetry_id(&self) -> u8 {
+        match self {
+            BaseKeymap::Zed => 0,
+            BaseKeymap::VSCode => 1,
+            BaseKeymap::JetBrains => 2,
+            BaseKeymap::SublimeText => 3,
+            BaseKeymap::Atom => 4,
+            BaseKeymap::TextMate => 5,
+            BaseKeymap::Emacs => 6,
+            BaseKeymap::Cursor => 7,
+            BaseKeymap::Vim => 8,
+            BaseKeymap::Helix => 9,
+            BaseKeymap::None => 10,
+            BaseKeymap::Nano => 11,
+            BaseKeymap::Kakoune => 12,
+            BaseKeymap::VisualStudio => 13,
+        }
+    }
+
+    /// Every base keymap, including [`BaseKeymap::None`] and those that are not
+    /// available on the current platform.
+    pub fn all() -> impl Iterator<Item = Self> {
+        [
+            Self::Zed,
+            Self::VSCode,
+            Self::JetBrains,
+            Self::SublimeText,
+            Self::Atom,
+            Self::TextMate,
+            Self::Emacs,
+            Self::Cursor,
+            Self::Vim,
+            Self::Helix,
+            Self::Nano,
+            Self::Kakoune,
+            Self::VisualStudio,
+            Self::None,
+        ]
+        .into_iter()
+    }
+
+    /// The base keymap recommended on first run for the current platform.
+    ///
+    /// Unlike [`BaseKeymap::default`], which is what an unset setting
+    /// deserializes to and so must not vary, this may differ between targets.
+    /// It is currently Zed everywhere.
+    pub const fn platform_default() -> Self {
+        Self::Zed
+    }
+
+    /// Whether any key bindings are loaded with this base keymap. This is only
+    /// false for [`BaseKeymap::None`], which leaves the user with no bindings
+    /// other than their own, so selecting it should be confirmed.
+    ///
+    /// Base keymaps without an [`Self::asset_path`], like [`BaseKeymap::Zed`],
+    /// still load the default bindings.
+    pub fn loads_bindings(&self) -> bool {
+        match self {
+            BaseKeymap::Zed
+            | BaseKeymap::VSCode
+            | BaseKeymap::JetBrains
+            | BaseKeymap::SublimeText
+            | BaseKeymap::Atom
+            | BaseKeymap::TextMate
+            | BaseKeymap::Emacs
+            | BaseKeymap::Cursor
+            | BaseKeymap::Vim
+            | BaseKeymap::Helix
+            | BaseKeymap::Nano
+            | BaseKeymap::Kakoune
+            | BaseKeymap::VisualStudio => true,
+            BaseKeymap::None => false,
+        }
+    }
+
+    /// Whether this base keymap can be selected on the current platform, which
+    /// is when it has a keymap asset here. [`BaseKeymap::Zed`] and
+    /// [`BaseKeymap::None`] don't need one, so they are always available.
+    pub fn is_available_on_platform(&self) -> bool {
+        matches!(self, Self::Zed | Self::None) || self.asset_path().is_some()
+    }
+
+    /// The base keymaps that can be selected on the current platform.
+    pub fn available() -> impl Iterator<Item = Self> {
+        Self::all().filter(Self::is_available_on_platform)
+    }
+
+    /// The name, description, and asset of every base keymap that can be
+    /// selected on the current platform.
+    pub fn catalog() -> impl Iterator<Item = BaseKeymapEntry> {
+        Self::available().map(|base_keymap| BaseKeymapEntry {
+            base_keymap,
+            name: base_keymap.display_name(),
+            description: base_keymap.description(),
+            asset_path: base_keymap.asset_path(),
+        })
+    }
+
+    /// The base keymaps that exist on other platforms, but can't be selected on
+    /// the current one.
+    pub fn unavailable() -> impl Iterator<Item = Self> {
+        Self::all().filter(|base_keymap| !base_keymap.is_available_on_platform())
+    }
+
+    /// Loads the keymap JSON for this base keymap from `assets`. Returns `None`
+    /// for base keymaps that don't have a keymap asset.
+    pub fn load_asset(
+        &self,
+        assets: &impl AssetSource,
+    ) -> anyhow::Result<Option<Cow<'static, [u8]>>> {
+        let Some(asset_path) = self.asset_path() else {
+            return Ok(None);
+        };
+        assets.load(asset_path)
+    }
+
     pub fn names() -> impl Iterator<Item = &'static str> {
         Self::OPTIONS.iter().map(|(name, _)| *name)
     }
 
-    pub fn from_names(option: &str) -> BaseKeymap {
+    /// Looks up a base keymap by one of the names in [`Self::OPTIONS`], ignoring
+    /// case, whitespace, and parenthesized notes like `(beta)`, so that e.g.
+    /// `"sublime text"` and `"VSCODE"` match. Returns `None` if no name matches.
+    pub fn try_from_name(option: &str) -> Option<BaseKeymap> {
+        fn normalize(name: &str) -> impl Iterator<Item = char> + '_ {
+            name.chars()
+                .take_while(|c| *c != '(')
+                .filter(|c| !c.is_whitespace())
+                .map(|c| c.to_ascii_lowercase())
+        }
+
         Self::OPTIONS
             .iter()
             .copied()
-            .find_map(|(name, value)| (name == option).then_some(value))
-            .unwrap_or_default()
+            .find_map(|(name, value)| normalize(name).eq(normalize(option)).then_some(value))
+    }
+
+    /// Like [`Self::try_from_name`], but falls back to the default base keymap
+    /// if no name matches.
+    pub fn from_names(option: &str) -> BaseKeymap {
+        Self::try_from_name(option).unwrap_or_default()
+    }
+
+    /// Returns the warning to log for a settings file whose `base_keymap` doesn't
+    /// name a base keymap, which loads as the default base keymap instead.
+    pub(crate) fn unknown_name_warning(settings_content: &str) -> Option<String> {
+        #[derive(Deserialize)]
+        struct BaseKeymapField {
+            base_keymap: Option<String>,
+        }
+
+        let name = crate::parse_json_with_comments::<BaseKeymapField>(settings_content)
+            .ok()?
+            .base_keymap?;
+        BaseKeymapContent::from_name(&name).is_none().then(|| {
+            format!(
+                "Unknown base keymap {name:?} in settings, using {} instead",
+                BaseKeymap::default()
+            )
+        })
+    }
+}
+
+/// Windows shares the Linux base keymaps, unless a Windows specific one is bundled.
+#[cfg(target_os = "windows")]
+fn windows_asset_path(windows_path: &'static str, linux_path: &'static str) -> &'static str {
+    if crate::SettingsAssets::get(windows_path).is_some() {
+        windows_path
+    } else {
+        linux_path
     }
 }
 
@@ -141,3 +455,444 @@ impl Settings for BaseKeymap {
         s.base_keymap.unwrap().into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zed_base_keymap() {
+        assert_eq!(BaseKeymap::from_names("Zed"), BaseKeymap::Zed);
+        assert_eq!(BaseKeymap::from_names("Zed (Default)"), BaseKeymap::Zed);
+        assert_eq!(BaseKeymap::Zed.to_string(), "Zed");
+        assert!(
+            BaseKeymap::OPTIONS
+                .iter()
+                .any(|(_, base_keymap)| *base_keymap == BaseKeymap::Zed)
+        );
+        assert_eq!(BaseKeymap::Zed.asset_path(), None);
+    }
+
+    #[test]
+    fn test_vim_base_keymap() {
+        assert_eq!(BaseKeymap::from_names("Vim"), BaseKeymap::Vim);
+        assert_eq!(BaseKeymap::Vim.to_string(), "Vim");
+
+        #[cfg(target_os = "macos")]
+        assert_eq!(BaseKeymap::Vim.asset_path(), Some("keymaps/macos/vim.json"));
+        #[cfg(target_os = "linux")]
+        assert_eq!(BaseKeymap::Vim.asset_path(), Some("keymaps/linux/vim.json"));
+        assert!(BaseKeymap::Vim.asset_path().is_some());
+    }
+
+    #[test]
+    fn test_helix_base_keymap() {
+        let (name, _) = BaseKeymap::OPTIONS
+            .iter()
+            .find(|(_, base_keymap)| *base_keymap == BaseKeymap::Helix)
+            .unwrap();
+        assert_eq!(BaseKeymap::from_names(name), BaseKeymap::Helix);
+        assert_eq!(BaseKeymap::Helix.to_string(), "Helix");
+        assert!(BaseKeymap::Helix.asset_path().is_some());
+    }
+
+    #[test]
+    fn test_nano_and_kakoune_base_keymaps() {
+        for (base_keymap, name, file_name) in [
+            (BaseKeymap::Nano, "Nano", "nano.json"),
+            (BaseKeymap::Kakoune, "Kakoune", "kakoune.json"),
+        ] {
+            assert_eq!(BaseKeymap::from_names(name), base_keymap);
+            assert_eq!(
+                BaseKeymap::from_names(&base_keymap.to_string()),
+                base_keymap
+            );
+            assert!(base_keymap.is_available_on_platform());
+
+            let asset_path = base_keymap.asset_path().unwrap();
+            #[cfg(target_os = "macos")]
+            assert_eq!(asset_path, format!("keymaps/macos/{file_name}"));
+            #[cfg(target_os = "linux")]
+            assert_eq!(asset_path, format!("keymaps/linux/{file_name}"));
+            assert!(asset_path.ends_with(file_name));
+            assert!(crate::SettingsAssets::get(asset_path).is_some());
+        }
+    }
+
+    #[test]
+    fn test_visual_studio_base_keymap() {
+        let base_keymap = BaseKeymap::VisualStudio;
+        assert_eq!(base_keymap.to_string(), "Visual Studio");
+        assert_eq!(BaseKeymap::from_names("Visual Studio"), base_keymap);
+        assert_eq!(
+            BaseKeymap::from_names(&base_keymap.to_string()),
+            base_keymap
+        );
+        assert_ne!(BaseKeymap::from_names("VS Code"), base_keymap);
+        assert!(base_keymap.is_available_on_platform());
+
+        let content: BaseKeymapContent = base_keymap.into();
+        assert_eq!(BaseKeymap::from(content), base_keymap);
+        let serialized = serde_json::to_string(&content).unwrap();
+        assert_eq!(serialized, r#""Visual Studio""#);
+        assert_eq!(
+            serde_json::from_str::<BaseKeymapContent>(&serialized).unwrap(),
+            content
+        );
+        assert_eq!(
+            serde_json::from_str::<BaseKeymapContent>(r#""VisualStudio""#).unwrap(),
+            content
+        );
+        assert_eq!(
+            serde_json::to_string(&base_keymap).unwrap(),
+            r#""VisualStudio""#
+        );
+
+        let asset_path = base_keymap.asset_path().unwrap();
+        #[cfg(target_os = "macos")]
+        assert_eq!(asset_path, "keymaps/macos/visual_studio.json");
+        #[cfg(target_os = "linux")]
+        assert_eq!(asset_path, "keymaps/linux/visual_studio.json");
+        assert!(crate::SettingsAssets::get(asset_path).is_some());
+
+        #[cfg(target_os = "windows")]
+        assert_eq!(
+            BaseKeymap::OPTIONS[2],
+            ("Visual Studio", BaseKeymap::VisualStudio)
+        );
+    }
+
+    #[test]
+    fn test_from_names_is_lenient() {
+        assert_eq!(
+            BaseKeymap::from_names("sublime text"),
+            BaseKeymap::SublimeText
+        );
+        assert_eq!(
+            BaseKeymap::from_names("  JetBrains  "),
+            BaseKeymap::JetBrains
+        );
+        assert_eq!(BaseKeymap::from_names("VSCODE"), BaseKeymap::VSCode);
+        assert_eq!(
+            BaseKeymap::from_names("not a keymap"),
+            BaseKeymap::default()
+        );
+    }
+
+    #[test]
+    fn test_unknown_base_keymap_in_settings() {
+        use crate::{ParseStatus, RootUserSettings, SettingsContent};
+
+        assert_eq!(BaseKeymapContent::from_name("Sublme Text"), None);
+        assert_eq!(
+            BaseKeymapContent::from_name("Sublime Text"),
+            Some(BaseKeymapContent::SublimeText)
+        );
+        assert_eq!(
+            BaseKeymapContent::from_name("SublimeText"),
+            Some(BaseKeymapContent::SublimeText)
+        );
+        assert_eq!(
+            serde_json::from_str::<BaseKeymapContent>(r#""Sublme Text""#).unwrap(),
+            BaseKeymapContent::default()
+        );
+
+        let (settings, status) = SettingsContent::parse_json(r#"{"base_keymap": "Sublme Text"}"#);
+        assert_eq!(status, ParseStatus::Success);
+        assert_eq!(
+            settings.unwrap().base_keymap,
+            Some(BaseKeymapContent::default())
+        );
+
+        assert_eq!(
+            BaseKeymap::unknown_name_warning(r#"{"base_keymap": "Sublme Text"}"#).as_deref(),
+            Some("Unknown base keymap \"Sublme Text\" in settings, using Zed instead")
+        );
+        for settings in [
+            r#"{"base_keymap": "Sublime Text"}"#,
+            r#"{"base_keymap": "SublimeText"}"#,
+            r#"{"vim_mode": true}"#,
+            "",
+        ] {
+            assert_eq!(
+                BaseKeymap::unknown_name_warning(settings),
+                None,
+                "{settings}"
+            );
+        }
+
+        assert_eq!(BaseKeymap::try_from_name("Sublme Text"), None);
+        assert_eq!(BaseKeymap::from_names("Sublme Text"), BaseKeymap::default());
+    }
+
+    #[test]
+    fn test_all() {
+        assert_eq!(BaseKeymap::all().count(), 14);
+        assert!(BaseKeymap::all().any(|base_keymap| base_keymap == BaseKeymap::None));
+        assert!(BaseKeymap::None.is_available_on_platform());
+        for (_, base_keymap) in BaseKeymap::OPTIONS {
+            assert!(BaseKeymap::all().any(|other| other == base_keymap));
+            assert!(base_keymap.is_available_on_platform());
+        }
+        #[cfg(not(target_os = "macos"))]
+        assert!(!BaseKeymap::TextMate.is_available_on_platform());
+    }
+
+    #[test]
+    fn test_available_partitions_all() {
+        assert_eq!(
+            BaseKeymap::available().count() + BaseKeymap::unavailable().count(),
+            BaseKeymap::all().count()
+        );
+        for (_, base_keymap) in BaseKeymap::OPTIONS {
+            assert!(BaseKeymap::available().any(|other| other == base_keymap));
+        }
+        assert!(BaseKeymap::available().any(|base_keymap| base_keymap == BaseKeymap::Zed));
+        assert!(BaseKeymap::available().any(|base_keymap| base_keymap == BaseKeymap::None));
+    }
+
+    #[test]
+    fn test_catalog() {
+        let catalog = BaseKeymap::catalog().collect::<Vec<_>>();
+        // Every option, plus `None`, which isn't offered as an option.
+        assert_eq!(catalog.len(), BaseKeymap::OPTIONS.len() + 1);
+        for (_, base_keymap) in BaseKeymap::OPTIONS {
+            assert!(catalog.iter().any(|entry| entry.base_keymap == base_keymap));
+        }
+        for entry in &catalog {
+            assert_eq!(entry.name, entry.base_keymap.to_string());
+            assert_eq!(entry.description, entry.base_keymap.description());
+            assert_eq!(entry.asset_path, entry.base_keymap.asset_path());
+        }
+        assert!(
+            catalog
+                .iter()
+                .any(|entry| entry.base_keymap == BaseKeymap::None)
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_textmate_unavailable_on_linux() {
+        assert!(BaseKeymap::unavailable().eq([BaseKeymap::TextMate]));
+        assert!(!BaseKeymap::available().any(|base_keymap| base_keymap == BaseKeymap::TextMate));
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_textmate_available_on_macos() {
+        assert!(BaseKeymap::available().any(|base_keymap| base_keymap == BaseKeymap::TextMate));
+        assert_eq!(BaseKeymap::unavailable().count(), 0);
+    }
+
+    #[test]
+    fn test_platform_default() {
+        assert_eq!(BaseKeymap::platform_default(), BaseKeymap::Zed);
+        assert!(BaseKeymap::platform_default().is_available_on_platform());
+        assert!(BaseKeymap::platform_default().loads_bindings());
+    }
+
+    #[test]
+    fn test_loads_bindings() {
+        assert!(!BaseKeymap::None.loads_bindings());
+        assert!(BaseKeymap::VSCode.loads_bindings());
+        assert!(BaseKeymap::JetBrains.loads_bindings());
+        assert!(BaseKeymap::JetBrains.asset_path().is_some());
+        assert!(BaseKeymap::Zed.loads_bindings());
+        assert!(BaseKeymap::Zed.asset_path().is_none());
+    }
+
+    #[test]
+    fn test_telemetry_ids() {
+        let ids = BaseKeymap::all()
+            .map(|base_keymap| (base_keymap, base_keymap.telemetry_id()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            [
+                (BaseKeymap::Zed, 0),
+                (BaseKeymap::VSCode, 1),
+                (BaseKeymap::JetBrains, 2),
+                (BaseKeymap::SublimeText, 3),
+                (BaseKeymap::Atom, 4),
+                (BaseKeymap::TextMate, 5),
+                (BaseKeymap::Emacs, 6),
+                (BaseKeymap::Cursor, 7),
+                (BaseKeymap::Vim, 8),
+                (BaseKeymap::Helix, 9),
+                (BaseKeymap::Nano, 11),
+                (BaseKeymap::Kakoune, 12),
+                (BaseKeymap::VisualStudio, 13),
+                (BaseKeymap::None, 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_descriptions() {
+        for base_keymap in BaseKeymap::all() {
+            assert!(
+                !base_keymap.description().is_empty(),
+                "{base_keymap:?} has no description"
+            );
+        }
+    }
+
+    #[test]
+    fn test_asset_paths_exist() {
+        for base_keymap in BaseKeymap::all() {
+            let Some(asset_path) = base_keymap.asset_path() else {
+                continue;
+            };
+            let asset = crate::SettingsAssets::get(asset_path).unwrap_or_else(|| {
+                panic!("{base_keymap:?} references missing keymap asset {asset_path}")
+            });
+            let content = std::str::from_utf8(&asset.data).unwrap();
+            if let Err(error) = crate::KeymapFile::parse(content) {
+                panic!("{base_keymap:?} keymap asset {asset_path} failed to parse: {error}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_schema_accepts_old_names() {
+        let schema = serde_json::to_value(schemars::schema_for!(BaseKeymapContent)).unwrap();
+        let accepted: Vec<&str> = schema["anyOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .flat_map(|alternative| alternative["enum"].as_array().unwrap())
+            .map(|name| name.as_str().unwrap())
+            .collect();
+        for name in [
+            "VS Code",
+            "VSCode",
+            "Sublime Text",
+            "SublimeText",
+            "Visual Studio",
+            "VisualStudio",
+        ] {
+            assert!(accepted.contains(&name), "{name} missing from {accepted:?}");
+            assert!(BaseKeymapContent::from_name(name).is_some(), "{name}");
+        }
+        for base_keymap in BaseKeymap::all() {
+            let content: BaseKeymapContent = base_keymap.into();
+            let name = serde_json::to_value(content).unwrap();
+            assert!(accepted.contains(&name.as_str().unwrap()), "{name}");
+            assert_eq!(
+                BaseKeymapContent::from_name(name.as_str().unwrap()),
+                Some(content)
+            );
+        }
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        // `BaseKeymap` is reported in telemetry, so it keeps serializing as its
+        // variant names.
+        for base_keymap in BaseKeymap::all() {
+            let serialized = serde_json::to_string(&base_keymap).unwrap();
+            let deserialized: BaseKeymap = serde_json::from_str(&serialized).unwrap();
+            assert_eq!(deserialized, base_keymap);
+        }
+        assert_eq!(
+            serde_json::to_string(&BaseKeymap::SublimeText).unwrap(),
+            r#""SublimeText""#
+        );
+        assert_eq!(
+            serde_json::to_string(&BaseKeymap::VSCode).unwrap(),
+            r#""VSCode""#
+        );
+
+        // Settings files use the names shown in the picker, and still accept
+        // the variant names they used before.
+        for base_keymap in BaseKeymap::all() {
+            let content: BaseKeymapContent = base_keymap.into();
+            let serialized = serde_json::to_string(&content).unwrap();
+            let deserialized: BaseKeymapContent = serde_json::from_str(&serialized).unwrap();
+            assert_eq!(BaseKeymap::from(deserialized), base_keymap);
+        }
+        for (_, base_keymap) in BaseKeymap::OPTIONS {
+            let content: BaseKeymapContent = base_keymap.into();
+            let serialized = serde_json::to_value(content).unwrap();
+            assert_eq!(
+                BaseKeymap::from_names(serialized.as_str().unwrap()),
+                base_keymap
+            );
+        }
+        assert_eq!(
+            serde_json::to_string(&BaseKeymapContent::SublimeText).unwrap(),
+            r#""Sublime Text""#
+        );
+        assert_eq!(
+            serde_json::to_string(&BaseKeymapContent::VSCode).unwrap(),
+            r#""VS Code""#
+        );
+        assert_eq!(
+            serde_json::from_str::<BaseKeymapContent>(r#""SublimeText""#).unwrap(),
+            BaseKeymapContent::SublimeText
+        );
+        assert_eq!(
+            serde_json::from_str::<BaseKeymapContent>(r#""VSCode""#).unwrap(),
+            BaseKeymapContent::VSCode
+        );
+    }
+
+    #[test]
+    fn test_load_asset() {
+        struct TestAssets;
+
+        impl AssetSource for TestAssets {
+            fn load(&self, path: &str) -> anyhow::Result<Option<Cow<'static, [u8]>>> {
+                if Some(path) == BaseKeymap::JetBrains.asset_path() {
+                    Ok(Some(Cow::Borrowed(b"[]")))
+                } else {
+                    Ok(None)
+                }
+            }
+
+            fn list(&self, _path: &str) -> anyhow::Result<Vec<gpui::SharedString>> {
+                Ok(Vec::new())
+            }
+        }
+
+        assert_eq!(
+            BaseKeymap::JetBrains
+                .load_asset(&TestAssets)
+                .unwrap()
+                .as_deref(),
+            Some(b"[]".as_slice())
+        );
+        assert_eq!(BaseKeymap::Atom.load_asset(&TestAssets).unwrap(), None);
+        assert_eq!(BaseKeymap::None.load_asset(&TestAssets).unwrap(), None);
+    }
+
+    #[test]
+    fn test_vscode_asset_path() {
+        let asset_path = BaseKeymap::VSCode.asset_path().unwrap();
+        #[cfg(target_os = "macos")]
+        assert_eq!(asset_path, "keymaps/macos/vscode.json");
+        #[cfg(target_os = "linux")]
+        assert_eq!(asset_path, "keymaps/linux/vscode.json");
+        assert!(crate::SettingsAssets::get(asset_path).is_some());
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn test_windows_asset_paths() {
+        for (_, base_keymap) in BaseKeymap::OPTIONS {
+            let Some(asset_path) = base_keymap.asset_path() else {
+                continue;
+            };
+            assert!(
+                asset_path.starts_with("keymaps/windows/")
+                    || asset_path.starts_with("keymaps/linux/"),
+                "{base_keymap:?} resolved to non-Windows asset {asset_path}"
+            );
+            assert!(
+                crate::SettingsAssets::get(asset_path).is_some(),
+                "{base_keymap:?} resolved to missing asset {asset_path}"
+            );
+        }
+    }
+}
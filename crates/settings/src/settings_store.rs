@@ -32,9 +32,9 @@ use util::{
 use crate::editorconfig_store::EditorconfigStore;
 
 use crate::{
-    ActiveSettingsProfileName, FileTypeMap, FontFamilyName, IconThemeName, LanguageSettingsContent,
-    LanguageToSettingsMap, LspSettings, LspSettingsMap, SemanticTokenRules, ThemeName,
-    UserSettingsContentExt, VsCodeSettings, WorktreeId,
+    ActiveSettingsProfileName, BaseKeymap, FileTypeMap, FontFamilyName, IconThemeName,
+    LanguageSettingsContent, LanguageToSettingsMap, LspSettings, LspSettingsMap,
+    SemanticTokenRules, ThemeName, UserSettingsContentExt, VsCodeSettings, WorktreeId,
     settings_content::{
         ExtendingSet, ExtensionsSettingsContent, ProfileBase, ProjectSettingsContent,
         RootUserSettings, SettingsContent, UserSettingsContent, merge_from::MergeFrom,
@@ -937,6 +937,9 @@ impl SettingsStore {
             };
         }
         self.last_user_settings_content = Some(user_settings_content.to_string());
+        if let Some(warning) = BaseKeymap::unknown_name_warning(user_settings_content) {
+            zlog::warn!("{warning}");
+        }
 
         let (settings, parse_result) = self.parse_and_migrate_zed_settings::<UserSettingsContent>(
             user_settings_content,
@@ -2201,7 +2204,7 @@ mod tests {
             .unindent(),
             r#" { "editor.tabSize": 37 } "#.to_owned(),
             r#"{
-              "base_keymap": "VSCode",
+              "base_keymap": "VS Code",
               "minimap": {
                 "show": "always"
               },
@@ -2222,7 +2225,7 @@ mod tests {
             .unindent(),
             r#"{ "editor.tabSize": 42 }"#.to_owned(),
             r#"{
-                "base_keymap": "VSCode",
+                "base_keymap": "VS Code",
                 "minimap": {
                     "show": "always"
                 },
@@ -2245,7 +2248,7 @@ mod tests {
             .unindent(),
             r#"{}"#.to_owned(),
             r#"{
-                "base_keymap": "VSCode",
+                "base_keymap": "VS Code",
                 "minimap": {
                     "show": "always"
                 },
@@ -2272,7 +2275,7 @@ mod tests {
               "outline_panel": {
                 "git_status": true
               },
-              "base_keymap": "VSCode",
+              "base_keymap": "VS Code",
               "tabs": {
                 "git_status": true
               },
@@ -2302,7 +2305,7 @@ mod tests {
                 "sort_mode": "mixed",
                 "sort_order": "lower"
               },
-              "base_keymap": "VSCode",
+              "base_keymap": "VS Code",
               "minimap": {
                 "show": "always"
               }
@@ -2321,7 +2324,7 @@ mod tests {
             .unindent(),
             r#"{ "editor.fontFamily": "Cascadia Code, 'Consolas', Courier New" }"#.to_owned(),
             r#"{
-              "base_keymap": "VSCode",
+              "base_keymap": "VS Code",
               "minimap": {
                 "show": "always"
               },
@@ -2348,7 +2351,7 @@ mod tests {
               "terminal": {
                 "bell": "system"
               },
-              "base_keymap": "VSCode",
+              "base_keymap": "VS Code",
               "minimap": {
                 "show": "always"
               }
@@ -2370,7 +2373,7 @@ mod tests {
               "terminal": {
                 "bell": "off"
               },
-              "base_keymap": "VSCode",
+              "base_keymap": "VS Code",
               "minimap": {
                 "show": "always"
               }
@@ -2392,7 +2395,7 @@ mod tests {
               "terminal": {
                 "bell": "system"
               },
-              "base_keymap": "VSCode",
+              "base_keymap": "VS Code",
               "minimap": {
                 "show": "always"
               }
@@ -2414,7 +2417,7 @@ mod tests {
               "terminal": {
                 "bell": "off"
               },
-              "base_keymap": "VSCode",
+              "base_keymap": "VS Code",
               "minimap": {
                 "show": "always"
               }
@@ -2440,7 +2443,7 @@ mod tests {
               "terminal": {
                 "bell": "off"
               },
-              "base_keymap": "VSCode",
+              "base_keymap": "VS Code",
               "minimap": {
                 "show": "always"
               }
@@ -2463,7 +2466,7 @@ mod tests {
             }"#
             .to_owned(),
             r#"{
-              "base_keymap": "VSCode",
+              "base_keymap": "VS Code",
               "minimap": {
                 "show": "always"
               },
@@ -2485,7 +2488,7 @@ mod tests {
             r#"{ "editor.formatOnSave": true, "editor.formatOnSaveMode": "modificationsIfAvailable" }"#
                 .to_owned(),
             r#"{
-              "base_keymap": "VSCode",
+              "base_keymap": "VS Code",
               "minimap": {
                 "show": "always"
               },
@@ -2506,7 +2509,7 @@ mod tests {
             r#"{ "editor.formatOnSave": true, "editor.formatOnSaveMode": "modifications" }"#
                 .to_owned(),
             r#"{
-              "base_keymap": "VSCode",
+              "base_keymap": "VS Code",
               "minimap": {
                 "show": "always"
               },
@@ -2526,7 +2529,7 @@ mod tests {
             .unindent(),
             r#"{ "editor.formatOnSave": true, "editor.formatOnSaveMode": "file" }"#.to_owned(),
             r#"{
-              "base_keymap": "VSCode",
+              "base_keymap": "VS Code",
               "minimap": {
                 "show": "always"
               },
@@ -2547,7 +2550,7 @@ mod tests {
             r#"{ "editor.formatOnSave": false, "editor.formatOnSaveMode": "modifications" }"#
                 .to_owned(),
             r#"{
-              "base_keymap": "VSCode",
+              "base_keymap": "VS Code",
               "minimap": {
                 "show": "always"
               },
@@ -2567,7 +2570,7 @@ mod tests {
             .unindent(),
             r#"{ "editor.formatOnSaveMode": "modifications" }"#.to_owned(),
             r#"{
-              "base_keymap": "VSCode",
+              "base_keymap": "VS Code",
               "minimap": {
                 "show": "always"
               }
@@ -2586,7 +2589,7 @@ mod tests {
             .unindent(),
             r#"{ "editor.formatOnSave": true }"#.to_owned(),
             r#"{
-              "base_keymap": "VSCode",
+              "base_keymap": "VS Code",
               "minimap": {
                 "show": "always"
               },
@@ -2606,7 +2609,7 @@ mod tests {
             .unindent(),
             r#"{ "editor.formatOnSave": false }"#.to_owned(),
             r#"{
-              "base_keymap": "VSCode",
+              "base_keymap": "VS Code",
               "minimap": {
                 "show": "always"
               },
@@ -2630,7 +2633,7 @@ mod tests {
             .unindent(),
             r#"{ "files.associations": { "*.keymap": "c" } }"#.to_owned(),
             r#"{
-              "base_keymap": "VSCode",
+              "base_keymap": "VS Code",
               "minimap": {
                 "show": "always"
               },
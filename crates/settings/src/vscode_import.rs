@@ -170,13 +170,35 @@ impl VsCodeSettings {
         (Some(fonts.remove(0)), skip_default(fonts))
     }
 
+    /// Settings.json doesn't list the installed extensions, so keymap extensions
+    /// are detected by the namespace of the settings they contribute.
+    fn base_keymap(&self) -> BaseKeymapContent {
+        const KEYMAP_EXTENSION_SETTING_PREFIXES: &[(&str, BaseKeymapContent)] = &[
+            // vscodevim.vim
+            ("vim.", BaseKeymapContent::Vim),
+            // k--kato.intellij-idea-keybindings
+            ("intellij-idea-keybindings.", BaseKeymapContent::JetBrains),
+            // ms-vscode.sublime-keybindings
+            ("sublimeTextKeymap.", BaseKeymapContent::SublimeText),
+            // ms-vscode.atom-keybindings
+            ("atomKeymap.", BaseKeymapContent::Atom),
+            // tuttieee.emacs-mcx
+            ("emacs-mcx.", BaseKeymapContent::Emacs),
+        ];
+
+        KEYMAP_EXTENSION_SETTING_PREFIXES
+            .iter()
+            .find(|(prefix, _)| self.content.keys().any(|key| key.starts_with(prefix)))
+            .map_or(BaseKeymapContent::VSCode, |(_, base_keymap)| *base_keymap)
+    }
+
     pub fn settings_content(&self) -> SettingsContent {
         SettingsContent {
             agent: self.agent_settings_content(),
             agent_servers: None,
             audio: None,
             auto_update: None,
-            base_keymap: Some(BaseKeymapContent::VSCode),
+            base_keymap: Some(self.base_keymap()),
             calls: None,
             collaboration_panel: None,
             credentials_url: None,
@@ -199,6 +221,12 @@ impl VsCodeSettings {
             language_models: None,
             line_indicator_format: None,
             log: None,
+            log_level: self.read_enum("editor.logLevel", |level| match level {
+                "trace" | "debug" | "info" | "error" | "off" => Some(level.to_owned()),
+                "warning" => Some("warn".to_owned()),
+                "critical" => Some("error".to_owned()),
+                _ => None,
+            }),
             node: self.node_binary_settings(),
 
             outline_panel: self.outline_panel_settings_content(),
@@ -1163,4 +1191,57 @@ mod tests {
         );
         assert_eq!(imported_reduce_motion("{}"), None);
     }
+
+    fn imported_base_keymap(content: &str) -> Option<BaseKeymapContent> {
+        VsCodeSettings::from_str(content, VsCodeSettingsSource::VsCode)
+            .unwrap()
+            .settings_content()
+            .base_keymap
+    }
+
+    #[test]
+    fn test_import_base_keymap() {
+        assert_eq!(
+            imported_base_keymap(
+                r#"{ "intellij-idea-keybindings.importFromIntelliJ": true, "editor.fontSize": 14 }"#
+            ),
+            Some(BaseKeymapContent::JetBrains)
+        );
+        assert_eq!(
+            imported_base_keymap(r#"{ "vim.leader": "<space>" }"#),
+            Some(BaseKeymapContent::Vim)
+        );
+        assert_eq!(
+            imported_base_keymap(r#"{ "editor.fontSize": 14 }"#),
+            Some(BaseKeymapContent::VSCode)
+        );
+    }
+
+    fn imported_log_level(content: &str) -> Option<String> {
+        VsCodeSettings::from_str(content, VsCodeSettingsSource::VsCode)
+            .unwrap()
+            .settings_content()
+            .log_level
+    }
+
+    #[test]
+    fn test_import_log_level() {
+        assert_eq!(
+            imported_log_level(r#"{ "editor.logLevel": "debug" }"#).as_deref(),
+            Some("debug")
+        );
+        assert_eq!(
+            imported_log_level(r#"{ "editor.logLevel": "warning" }"#).as_deref(),
+            Some("warn")
+        );
+        assert_eq!(
+            imported_log_level(r#"{ "editor.logLevel": "critical" }"#).as_deref(),
+            Some("error")
+        );
+        assert_eq!(
+            imported_log_level(r#"{ "editor.logLevel": "verbose" }"#),
+            None
+        );
+        assert_eq!(imported_log_level("{}"), None);
+    }
 }
@@ -230,6 +230,12 @@ pub struct SettingsContent {
     /// Example: {"log": {"client": "warn"}}
     pub log: Option<HashMap<String, String>>,
 
+    /// The log level of scopes that aren't configured in `log`. Overridden by
+    /// a global level set with the `ZED_LOG` environment variable.
+    ///
+    /// Default: "info"
+    pub log_level: Option<String>,
+
     pub line_indicator_format: Option<LineIndicatorFormat>,
 
     pub language_models: Option<AllLanguageModelSettingsContent>,
@@ -465,39 +471,95 @@ pub struct ExtensionsSettingsContent {
     Clone,
     Debug,
     Serialize,
-    Deserialize,
-    JsonSchema,
     MergeFrom,
     PartialEq,
     Eq,
     Default,
     strum::VariantArray,
+    strum::VariantNames,
 )]
 pub enum BaseKeymapContent {
     #[default]
     Zed,
+    #[serde(rename = "VS Code")]
+    #[strum(serialize = "VS Code")]
     VSCode,
     JetBrains,
+    #[serde(rename = "Sublime Text")]
+    #[strum(serialize = "Sublime Text")]
     SublimeText,
     Atom,
     TextMate,
     Emacs,
     Cursor,
+    Vim,
+    Helix,
+    Nano,
+    Kakoune,
+    #[serde(rename = "Visual Studio")]
+    #[strum(serialize = "Visual Studio")]
+    VisualStudio,
     None,
 }
 
-impl strum::VariantNames for BaseKeymapContent {
-    const VARIANTS: &'static [&'static str] = &[
-        "Zed",
-        "VSCode",
-        "JetBrains",
-        "Sublime Text",
-        "Atom",
-        "TextMate",
-        "Emacs",
-        "Cursor",
-        "None",
+impl BaseKeymapContent {
+    /// The variant names older settings files used for base keymaps that have
+    /// since been renamed.
+    const OLD_NAMES: &[(&str, Self)] = &[
+        ("VSCode", Self::VSCode),
+        ("SublimeText", Self::SublimeText),
+        ("VisualStudio", Self::VisualStudio),
     ];
+
+    /// Looks up a base keymap by the name settings files use for it, or by the
+    /// variant name older settings files used, e.g. `"VSCode"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        <Self as strum::VariantArray>::VARIANTS
+            .iter()
+            .zip(<Self as strum::VariantNames>::VARIANTS)
+            .chain(
+                Self::OLD_NAMES
+                    .iter()
+                    .map(|(old_name, variant)| (variant, old_name)),
+            )
+            .find_map(|(variant, variant_name)| (*variant_name == name).then_some(*variant))
+    }
+}
+
+impl JsonSchema for BaseKeymapContent {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "BaseKeymapContent".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        let old_names: Vec<&str> = Self::OLD_NAMES.iter().map(|(name, _)| *name).collect();
+        // Old names still load, so they're accepted, but not suggested.
+        schemars::json_schema!({
+            "anyOf": [
+                {
+                    "type": "string",
+                    "enum": <Self as strum::VariantNames>::VARIANTS
+                },
+                {
+                    "type": "string",
+                    "enum": old_names,
+                    "deprecated": true
+                }
+            ]
+        })
+    }
+}
+
+// Unknown names load as the default base keymap instead of failing the whole
+// settings file. The settings store warns about them when it loads the file.
+impl<'de> Deserialize<'de> for BaseKeymapContent {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let name = std::borrow::Cow::<'de, str>::deserialize(deserializer)?;
+        Ok(Self::from_name(&name).unwrap_or_default())
+    }
 }
 
 /// Configuration of audio in Zed.
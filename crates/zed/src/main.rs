@@ -292,14 +292,23 @@ fn main() {
 
     zlog::init();
 
-    if stdout_is_a_pty() {
+    if zlog::has_output_from_env() {
+        // `ZED_LOG_OUTPUT` already selected where logs go
+    } else if stdout_is_a_pty() {
         zlog::init_output_stdout();
     } else {
-        let result = zlog::init_output_file(paths::log_file(), Some(paths::old_log_file()));
-        if let Err(err) = result {
-            eprintln!("Could not open log file: {}... Defaulting to stdout", err);
-            zlog::init_output_stdout();
-        };
+        let result = zlog::init_output_file(
+            paths::log_file(),
+            Some(paths::old_log_file()),
+            zlog::OpenMode::Append,
+        );
+        match result {
+            Ok(()) => zlog::init_output_fallback(Box::new(std::io::stderr())),
+            Err(err) => {
+                eprintln!("Could not open log file: {}... Defaulting to stdout", err);
+                zlog::init_output_stdout();
+            }
+        }
     }
     ztracing::init();
 
@@ -327,14 +336,17 @@ fn main() {
         .build_global()
         .unwrap();
 
-    log::info!(
-        "========== starting zed version {}, sha {} ==========",
-        app_version,
-        app_commit_sha
-            .as_ref()
-            .map(|sha| sha.short())
-            .as_deref()
-            .unwrap_or("unknown"),
+    zlog::log_banner(
+        "zed",
+        &format!(
+            "{}, sha {}",
+            app_version,
+            app_commit_sha
+                .as_ref()
+                .map(|sha| sha.short())
+                .as_deref()
+                .unwrap_or("unknown"),
+        ),
     );
 
     #[cfg(windows)]
@@ -0,0 +1,119 @@
+use collections::HashMap;
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use zlog::filter::ScopeMap;
+
+const SCOPE_COUNTS: [usize; 3] = [4, 32, 256];
+
+fn is_enabled(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("scope_map_is_enabled");
+
+    for scope_count in SCOPE_COUNTS {
+        let settings = (0..scope_count)
+            .map(|i| (format!("crate{i}.sub{}", i % 7), "debug".to_string()))
+            .collect::<HashMap<_, _>>();
+        let map = ScopeMap::new_from_settings_and_env(&settings, None, &[]);
+        let hit = [
+            format!("crate{}", scope_count - 1),
+            format!("sub{}", (scope_count - 1) % 7),
+            String::new(),
+            String::new(),
+        ];
+        let miss = [
+            "missing".to_string(),
+            String::new(),
+            String::new(),
+            String::new(),
+        ];
+
+        group.bench_with_input(
+            BenchmarkId::new("hit", scope_count),
+            &scope_count,
+            |bench, _| {
+                bench.iter(|| map.is_enabled(&hit, Some("crate::module"), log::Level::Debug));
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("miss", scope_count),
+            &scope_count,
+            |bench, _| {
+                bench.iter(|| map.is_enabled(&miss, Some("crate::module"), log::Level::Debug));
+            },
+        );
+    }
+
+    group.finish();
+}
+
+const RECORD_COUNT: usize = 1024;
+
+/// Compares logging to a scope built at runtime, which is checked with
+/// `is_scope_alloc_enabled` and submitted by borrowing its strings, with
+/// logging to a static scope. They should be within a small factor of each
+/// other, as neither allocates beyond rendering the message.
+fn static_vs_owned_scope(criterion: &mut Criterion) {
+    zlog::init_output_writer(Box::new(std::io::sink()));
+    let settings = (0..32)
+        .map(|i| (format!("crate{i}.sub{}", i % 7), "debug".to_string()))
+        .chain([("zlog.bench".to_string(), "info".to_string())])
+        .collect::<HashMap<_, _>>();
+    zlog::filter::refresh_from_settings(&settings);
+
+    let static_scope = ["zlog", "bench", "", ""];
+    let owned_scope = static_scope.map(str::to_string);
+
+    let mut group = criterion.benchmark_group("is_scope_enabled");
+    group.bench_function("static", |bench| {
+        bench.iter(|| {
+            zlog::filter::is_scope_enabled(&static_scope, Some("zlog::bench"), log::Level::Info)
+        });
+    });
+    group.bench_function("owned", |bench| {
+        bench.iter(|| {
+            zlog::filter::is_scope_alloc_enabled(
+                &owned_scope,
+                Some("zlog::bench"),
+                log::Level::Info,
+            )
+        });
+    });
+    group.finish();
+
+    let mut group = criterion.benchmark_group("log_records");
+    group.throughput(Throughput::Elements(RECORD_COUNT as u64));
+    group.bench_function("static", |bench| {
+        bench.iter(|| {
+            for i in 0..RECORD_COUNT {
+                if zlog::filter::is_scope_enabled(&static_scope, None, log::Level::Info) {
+                    zlog::sink::submit(zlog::sink::Record {
+                        scope: static_scope,
+                        level: log::Level::Info,
+                        message: &format_args!("benchmark record {i}"),
+                        module_path: None,
+                        line: None,
+                        seq: 0,
+                    });
+                }
+            }
+        });
+    });
+    group.bench_function("owned", |bench| {
+        bench.iter(|| {
+            for i in 0..RECORD_COUNT {
+                if zlog::filter::is_scope_alloc_enabled(&owned_scope, None, log::Level::Info) {
+                    zlog::sink::submit(zlog::sink::Record {
+                        scope: owned_scope.each_ref().map(String::as_str),
+                        level: log::Level::Info,
+                        message: &format_args!("benchmark record {i}"),
+                        module_path: None,
+                        line: None,
+                        seq: 0,
+                    });
+                }
+            }
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, is_enabled, static_vs_owned_scope);
+criterion_main!(benches);
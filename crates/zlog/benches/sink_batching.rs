@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+const RECORD_COUNT: usize = 1024;
+const BATCH_SIZES: [usize; 4] = [1, 16, 64, 256];
+
+fn submit_records(criterion: &mut Criterion) {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let log_file_path: &'static PathBuf = Box::leak(Box::new(temp_dir.path().join("bench.log")));
+    let rotated_log_file_path: &'static PathBuf =
+        Box::leak(Box::new(temp_dir.path().join("bench.log.old")));
+    zlog::init_output_file(
+        log_file_path,
+        Some(rotated_log_file_path),
+        zlog::OpenMode::Truncate,
+    )
+    .unwrap();
+
+    let mut group = criterion.benchmark_group("submit_records");
+    group.throughput(Throughput::Elements(RECORD_COUNT as u64));
+
+    for batch_size in BATCH_SIZES {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(batch_size),
+            &batch_size,
+            |bench, &batch_size| {
+                zlog::sink::set_batch_size(batch_size);
+                bench.iter(|| {
+                    for i in 0..RECORD_COUNT {
+                        zlog::sink::submit(zlog::sink::Record {
+                            scope: ["zlog", "bench", "", ""],
+                            level: log::Level::Info,
+                            message: &format_args!("benchmark record {i}"),
+                            module_path: Some(module_path!()),
+                            line: None,
+                            seq: 0,
+                        });
+                    }
+                    zlog::flush();
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, submit_records);
+criterion_main!(benches);
@@ -0,0 +1,173 @@
+//! Per-thread key-value context that is attached to every record submitted on
+//! the thread, e.g. a request id that should appear on all logs for a task.
+
+use std::cell::{Cell, RefCell};
+
+thread_local! {
+    static CONTEXT: RefCell<Vec<(&'static str, String)>> = const { RefCell::new(Vec::new()) };
+    /// The fields of the logger that is submitting a record, see
+    /// `Logger::with_fields`.
+    static LOGGER_FIELDS: Cell<&'static [(&'static str, &'static str)]> = const { Cell::new(&[]) };
+}
+
+/// Sets `key` to `value` in the calling thread's context, replacing any
+/// previous value.
+pub fn set(key: &'static str, value: impl Into<String>) {
+    let value = value.into();
+    CONTEXT.with_borrow_mut(|context| {
+        match context.iter_mut().find(|(existing, _)| *existing == key) {
+            Some((_, existing)) => *existing = value,
+            None => context.push((key, value)),
+        }
+    });
+}
+
+/// Removes `key` from the calling thread's context, returning its value.
+pub fn remove(key: &'static str) -> Option<String> {
+    CONTEXT.with_borrow_mut(|context| {
+        let index = context.iter().position(|(existing, _)| *existing == key)?;
+        Some(context.remove(index).1)
+    })
+}
+
+/// Removes every entry from the calling thread's context.
+pub fn clear() {
+    CONTEXT.with_borrow_mut(Vec::clear);
+}
+
+/// Sets `key` to `value` until the returned guard is dropped, at which point
+/// the previous value (if any) is restored.
+#[must_use]
+pub fn scoped(key: &'static str, value: impl Into<String>) -> ContextGuard {
+    let previous = CONTEXT.with_borrow(|context| {
+        context
+            .iter()
+            .find(|(existing, _)| *existing == key)
+            .map(|(_, value)| value.clone())
+    });
+    set(key, value);
+    ContextGuard { key, previous }
+}
+
+pub struct ContextGuard {
+    key: &'static str,
+    previous: Option<String>,
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        match self.previous.take() {
+            Some(previous) => set(self.key, previous),
+            None => _ = remove(self.key),
+        }
+    }
+}
+
+/// Calls `f` with the calling thread's context, or with no entries if the
+/// thread is exiting and its context has already been destroyed. While a
+/// logger with fields submits a record, its fields come first, except for keys
+/// that the context sets itself.
+pub(crate) fn with_current<R>(f: impl FnOnce(&[(&'static str, String)]) -> R) -> R {
+    let logger_fields = LOGGER_FIELDS.try_with(Cell::get).unwrap_or_default();
+    let mut f = Some(f);
+    CONTEXT
+        .try_with(|context| {
+            let context = context.borrow();
+            if logger_fields.is_empty() {
+                return (f.take().unwrap())(&context);
+            }
+            let merged = logger_fields
+                .iter()
+                .filter(|(key, _)| !context.iter().any(|(existing, _)| existing == key))
+                .map(|(key, value)| (*key, value.to_string()))
+                .chain(context.iter().cloned())
+                .collect::<Vec<_>>();
+            (f.take().unwrap())(&merged)
+        })
+        .unwrap_or_else(|_| (f.take().unwrap())(&[]))
+}
+
+/// Calls `f` with `fields` attached to the records it submits on the calling
+/// thread, as the fields of the logger they are submitted through.
+pub(crate) fn with_logger_fields<R>(
+    fields: &'static [(&'static str, &'static str)],
+    f: impl FnOnce() -> R,
+) -> R {
+    if fields.is_empty() {
+        return f();
+    }
+    struct Restore(&'static [(&'static str, &'static str)]);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            _ = LOGGER_FIELDS.try_with(|current| current.set(self.0));
+        }
+    }
+    let _restore = LOGGER_FIELDS
+        .try_with(|current| current.replace(fields))
+        .map(Restore);
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn current() -> Vec<(&'static str, String)> {
+        with_current(<[_]>::to_vec)
+    }
+
+    #[test]
+    fn test_set_remove_clear() {
+        set("request_id", "1");
+        set("user", "someone");
+        set("request_id", "2");
+        assert_eq!(
+            current(),
+            [
+                ("request_id", "2".to_string()),
+                ("user", "someone".to_string())
+            ]
+        );
+
+        assert_eq!(remove("user"), Some("someone".to_string()));
+        assert_eq!(remove("user"), None);
+        assert_eq!(current(), [("request_id", "2".to_string())]);
+
+        clear();
+        assert!(current().is_empty());
+    }
+
+    #[test]
+    fn test_logger_fields() {
+        let _guard = scoped("request_id", "7");
+        let _override = scoped("component", "call_site");
+        let merged = with_logger_fields(&[("component", "parser"), ("stage", "lex")], current);
+        assert_eq!(
+            merged,
+            [
+                ("stage", "lex".to_string()),
+                ("request_id", "7".to_string()),
+                ("component", "call_site".to_string()),
+            ]
+        );
+        assert_eq!(current().len(), 2);
+    }
+
+    #[test]
+    fn test_scoped() {
+        set("request_id", "outer");
+        {
+            let _guard = scoped("request_id", "inner");
+            let _other = scoped("task", "indexing");
+            assert_eq!(
+                current(),
+                [
+                    ("request_id", "inner".to_string()),
+                    ("task", "indexing".to_string())
+                ]
+            );
+        }
+        assert_eq!(current(), [("request_id", "outer".to_string())]);
+        clear();
+    }
+}
@@ -1,31 +1,193 @@
-use anyhow::Result;
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+#[cfg(feature = "std")]
+use std::path::PathBuf;
 
+/// A parsed `ZED_LOG` filter. A scope takes the level of the most specific
+/// directive that applies to it: a directive naming the scope or one of its
+/// parents, then the `*` wildcard directive, then the bare global level.
 pub struct EnvFilter {
     pub level_global: Option<log::LevelFilter>,
+    /// The level of the `*` directive, which applies to every scope without a
+    /// directive of its own and overrides `level_global`.
+    pub level_wildcard: Option<log::LevelFilter>,
     pub directive_names: Vec<String>,
     pub directive_levels: Vec<log::LevelFilter>,
+    /// Directives with a `@n/m` suffix, whose records are sampled.
+    pub sampling: Vec<(String, Sampling)>,
 }
 
-pub fn parse(filter: &str) -> Result<EnvFilter> {
+/// Emits `n` out of every `m` records that are otherwise enabled, as set with
+/// a `@n/m` directive suffix, e.g. `editor::sync=trace@1/100`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sampling {
+    pub n: u32,
+    pub m: u32,
+}
+
+impl fmt::Display for Sampling {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.n, self.m)
+    }
+}
+
+/// Parses `n/m` with `0 < n <= m`.
+fn parse_sampling(sampling: &str) -> Option<Sampling> {
+    let (n, m) = sampling.split_once('/')?;
+    let n = n.trim().parse().ok()?;
+    let m = m.trim().parse().ok()?;
+    (0 < n && n <= m).then_some(Sampling { n, m })
+}
+
+impl EnvFilter {
+    /// The level of scopes that no specific directive applies to.
+    pub fn level_default(&self) -> Option<log::LevelFilter> {
+        self.level_wildcard.or(self.level_global)
+    }
+
+    /// Renders the filter in the syntax accepted by `parse`: the global level
+    /// first, then the wildcard directive, then each directive sorted by name. When a name is repeated only
+    /// the last directive is kept, as it is the one that takes effect.
+    pub fn to_env_string(&self) -> String {
+        let mut directives = BTreeMap::new();
+        for (name, level) in self.directive_names.iter().zip(&self.directive_levels) {
+            if !name.is_empty() {
+                directives.insert(name.as_str(), *level);
+            }
+        }
+        let sampling = |name: &str| {
+            self.sampling
+                .iter()
+                .rfind(|(sampled, _)| sampled == name)
+                .map(|(_, sampling)| alloc::format!("@{sampling}"))
+                .unwrap_or_default()
+        };
+        self.level_global
+            .map(|level| level.as_str().to_ascii_lowercase())
+            .into_iter()
+            .chain(
+                self.level_wildcard
+                    .map(|level| format!("*={}", level.as_str().to_ascii_lowercase())),
+            )
+            .chain(directives.into_iter().map(|(name, level)| {
+                format!(
+                    "{name}={}{}",
+                    level.as_str().to_ascii_lowercase(),
+                    sampling(name)
+                )
+            }))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// The offending part of the filter.
+    pub span: String,
+    /// The byte offset of `span` within the filter.
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    InvalidLevel,
+    InvalidDirective,
+    MultipleMaxLevels,
+    InvalidSampling,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { span, offset, .. } = self;
+        match self.kind {
+            ParseErrorKind::InvalidLevel => write!(
+                f,
+                "invalid level '{span}' at position {offset}, expected one of: trace, debug, info, warn, error, off"
+            ),
+            ParseErrorKind::InvalidDirective => {
+                write!(f, "invalid directive '{span}' at position {offset}")
+            }
+            ParseErrorKind::MultipleMaxLevels => write!(
+                f,
+                "cannot set multiple max levels, found '{span}' at position {offset}"
+            ),
+            ParseErrorKind::InvalidSampling => write!(
+                f,
+                "invalid sampling ratio '{span}' at position {offset}, expected n/m with 0 < n <= m"
+            ),
+        }
+    }
+}
+
+impl core::error::Error for ParseError {}
+
+pub fn parse(filter: &str) -> Result<EnvFilter, ParseError> {
     let mut max_level = None;
+    let mut wildcard_level = None;
     let mut directive_names = Vec::new();
     let mut directive_levels = Vec::new();
+    let mut sampling = Vec::new();
 
+    let mut directive_offset = 0;
     for directive in filter.split(',') {
+        let offset = directive_offset;
+        directive_offset += directive.len() + 1;
+        let error = |kind, span: &str| ParseError {
+            kind,
+            span: span.to_string(),
+            // `span` is always a subslice of `directive`
+            offset: offset + (span.as_ptr() as usize - directive.as_ptr() as usize),
+        };
         match directive.split_once('=') {
             Some((name, level)) => {
-                anyhow::ensure!(!level.contains('='), "Invalid directive: {directive}");
-                let level = parse_level(level.trim())?;
-                directive_names.push(name.trim().trim_end_matches(".rs").to_string());
+                if level.contains('=') {
+                    return Err(error(ParseErrorKind::InvalidDirective, directive.trim()));
+                }
+                let (level_str, sampling_str) = match level.split_once('@') {
+                    Some((level, sampling)) => (level.trim(), Some(sampling.trim())),
+                    None => (level.trim(), None),
+                };
+                let level = parse_level(level_str)
+                    .map_err(|_| error(ParseErrorKind::InvalidLevel, level_str))?;
+                let sampled = sampling_str
+                    .map(|sampling_str| {
+                        parse_sampling(sampling_str)
+                            .ok_or_else(|| error(ParseErrorKind::InvalidSampling, sampling_str))
+                    })
+                    .transpose()?;
+                let name = name.trim();
+                if name == "*" {
+                    wildcard_level = Some(level);
+                    continue;
+                }
+                let name = name.trim_end_matches(".rs");
+                if let Some(sampled) = sampled {
+                    sampling.push((name.to_string(), sampled));
+                }
+                directive_names.push(name.to_string());
                 directive_levels.push(level);
             }
             None => {
-                let Ok(level) = parse_level(directive.trim()) else {
-                    directive_names.push(directive.trim().trim_end_matches(".rs").to_string());
+                let directive = directive.trim();
+                let Ok(level) = parse_level(directive) else {
+                    if directive == "*" {
+                        wildcard_level = Some(log::LevelFilter::max());
+                        continue;
+                    }
+                    directive_names.push(directive.trim_end_matches(".rs").to_string());
                     directive_levels.push(log::LevelFilter::max() /* Enable all levels */);
                     continue;
                 };
-                anyhow::ensure!(max_level.is_none(), "Cannot set multiple max levels");
+                if max_level.is_some() {
+                    return Err(error(ParseErrorKind::MultipleMaxLevels, directive));
+                }
                 max_level.replace(level);
             }
         };
@@ -33,37 +195,126 @@ pub fn parse(filter: &str) -> Result<EnvFilter> {
 
     Ok(EnvFilter {
         level_global: max_level,
+        level_wildcard: wildcard_level,
         directive_names,
         directive_levels,
+        sampling,
     })
 }
 
-fn parse_level(level: &str) -> Result<log::LevelFilter> {
-    if level.eq_ignore_ascii_case("TRACE") {
-        return Ok(log::LevelFilter::Trace);
-    }
-    if level.eq_ignore_ascii_case("DEBUG") {
-        return Ok(log::LevelFilter::Debug);
+/// Joins the directives of a filter file, given as `ZED_LOG=@<path>`, into
+/// the syntax accepted by `parse`. Each line holds one or more directives, and
+/// `#` starts a comment that runs to the end of the line.
+#[cfg(feature = "std")]
+pub fn filter_file_directives(contents: &str) -> String {
+    let mut directives = String::new();
+    for line in contents.lines() {
+        let line = line.split_once('#').map_or(line, |(line, _)| line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if !directives.is_empty() {
+            directives.push(',');
+        }
+        directives.push_str(line);
     }
-    if level.eq_ignore_ascii_case("INFO") {
-        return Ok(log::LevelFilter::Info);
-    }
-    if level.eq_ignore_ascii_case("WARN") {
-        return Ok(log::LevelFilter::Warn);
+    directives
+}
+
+/// Where to send log output, as selected by `ZED_LOG_OUTPUT`.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputTarget {
+    Stdout,
+    Stderr,
+    File(PathBuf),
+}
+
+#[cfg(feature = "std")]
+pub fn parse_output(output: &str) -> Option<OutputTarget> {
+    let output = output.trim();
+    if output.eq_ignore_ascii_case("stdout") {
+        return Some(OutputTarget::Stdout);
     }
-    if level.eq_ignore_ascii_case("ERROR") {
-        return Ok(log::LevelFilter::Error);
+    if output.eq_ignore_ascii_case("stderr") {
+        return Some(OutputTarget::Stderr);
     }
-    if level.eq_ignore_ascii_case("OFF") || level.eq_ignore_ascii_case("NONE") {
-        return Ok(log::LevelFilter::Off);
+    let path = output.strip_prefix("file:")?;
+    if path.is_empty() {
+        return None;
     }
-    anyhow::bail!("Invalid level: {level}")
+    Some(OutputTarget::File(PathBuf::from(path)))
+}
+
+/// Parses a level name, ignoring case, as accepted both in `ZED_LOG` and in the
+/// log settings. Besides the `log::LevelFilter` names, the aliases
+/// `information`, `warning`, `err` and `none` are accepted.
+pub fn parse_level(level: &str) -> Result<log::LevelFilter, ParseError> {
+    use log::LevelFilter::*;
+    let level_filter = match level.to_ascii_lowercase().as_str() {
+        "trace" => Trace,
+        "debug" => Debug,
+        "info" | "information" => Info,
+        "warn" | "warning" => Warn,
+        "error" | "err" => Error,
+        "off" | "none" => Off,
+        _ => {
+            return Err(ParseError {
+                kind: ParseErrorKind::InvalidLevel,
+                span: level.to_string(),
+                offset: 0,
+            });
+        }
+    };
+    Ok(level_filter)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn parse_level_names_and_aliases() {
+        use log::LevelFilter;
+        for (level, expected) in [
+            ("trace", LevelFilter::Trace),
+            ("DEBUG", LevelFilter::Debug),
+            ("info", LevelFilter::Info),
+            ("Information", LevelFilter::Info),
+            ("warn", LevelFilter::Warn),
+            ("warning", LevelFilter::Warn),
+            ("error", LevelFilter::Error),
+            ("err", LevelFilter::Error),
+            ("off", LevelFilter::Off),
+            ("None", LevelFilter::Off),
+        ] {
+            assert_eq!(parse_level(level), Ok(expected), "{level}");
+        }
+
+        for level in ["", "verbose", "disabled", " info"] {
+            let err = parse_level(level).unwrap_err();
+            assert_eq!(err.kind, ParseErrorKind::InvalidLevel);
+            assert_eq!(err.span, level);
+        }
+    }
+
+    #[test]
+    fn to_env_string_round_trip() {
+        let filter = parse(
+            "Warning, project=debug,editor.sub = trace,project=info,worktree::scan,agent=none",
+        )
+        .unwrap();
+        let env_string = filter.to_env_string();
+        assert_eq!(
+            env_string,
+            "warn,agent=off,editor.sub=trace,project=info,worktree::scan=trace"
+        );
+        assert_eq!(parse(&env_string).unwrap().to_env_string(), env_string);
+
+        assert_eq!(parse("debug").unwrap().to_env_string(), "debug");
+        assert_eq!(parse("a=error").unwrap().to_env_string(), "a=error");
+    }
+
     #[test]
     fn global_level() {
         let input = "info";
@@ -104,6 +355,85 @@ mod tests {
         assert_eq!(filter.directive_levels, vec![log::LevelFilter::max()]);
     }
 
+    #[test]
+    fn wildcard_directive() {
+        let filter = parse("warn,*=info,editor=debug").unwrap();
+        assert_eq!(filter.level_global, Some(log::LevelFilter::Warn));
+        assert_eq!(filter.level_wildcard, Some(log::LevelFilter::Info));
+        assert_eq!(filter.level_default(), Some(log::LevelFilter::Info));
+        assert_eq!(filter.directive_names, vec!["editor".to_string()]);
+        assert_eq!(filter.to_env_string(), "warn,*=info,editor=debug");
+
+        let filter = parse("*").unwrap();
+        assert_eq!(filter.level_global, None);
+        assert_eq!(filter.level_wildcard, Some(log::LevelFilter::max()));
+        assert!(filter.directive_names.is_empty());
+
+        let filter = parse("warn,editor=debug").unwrap();
+        assert_eq!(filter.level_wildcard, None);
+        assert_eq!(filter.level_default(), Some(log::LevelFilter::Warn));
+    }
+
+    #[test]
+    fn sampling_suffix() {
+        let filter = parse("info,editor::sync=trace@1/100,project = debug @ 3/4").unwrap();
+        assert_eq!(
+            filter.directive_names,
+            vec!["editor::sync".to_string(), "project".to_string()]
+        );
+        assert_eq!(
+            filter.directive_levels,
+            vec![log::LevelFilter::Trace, log::LevelFilter::Debug]
+        );
+        assert_eq!(
+            filter.sampling,
+            vec![
+                ("editor::sync".to_string(), Sampling { n: 1, m: 100 }),
+                ("project".to_string(), Sampling { n: 3, m: 4 }),
+            ]
+        );
+        assert_eq!(
+            filter.to_env_string(),
+            "info,editor::sync=trace@1/100,project=debug@3/4"
+        );
+
+        for (input, span, offset) in [
+            ("editor=trace@0/10", "0/10", 13),
+            ("editor=trace@5/2", "5/2", 13),
+            ("info,editor=trace@1/0", "1/0", 18),
+            ("editor=trace@1", "1", 13),
+            ("editor=trace@a/b", "a/b", 13),
+            ("editor=trace@", "", 13),
+        ] {
+            let err = parse(input).err().unwrap();
+            assert_eq!(err.kind, ParseErrorKind::InvalidSampling, "{input}");
+            assert_eq!(err.span, span, "{input}");
+            assert_eq!(err.offset, offset, "{input}");
+        }
+        let err = parse("editor=trace@1/0").err().unwrap();
+        assert!(
+            err.to_string()
+                .starts_with("invalid sampling ratio '1/0' at position 13"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn directive_level_off() {
+        let input = "debug,editor=off,project=none";
+        let filter = parse(input).unwrap();
+
+        assert_eq!(filter.level_global.unwrap(), log::LevelFilter::Debug);
+        assert_eq!(
+            filter.directive_names,
+            vec!["editor".to_string(), "project".to_string()]
+        );
+        assert_eq!(
+            filter.directive_levels,
+            vec![log::LevelFilter::Off, log::LevelFilter::Off]
+        );
+    }
+
     #[test]
     fn err_when_multiple_max_levels() {
         let input = "info,warn";
@@ -112,11 +442,82 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn level_aliases() {
+        for (alias, level) in [
+            ("warning", log::LevelFilter::Warn),
+            ("WARNING", log::LevelFilter::Warn),
+            ("err", log::LevelFilter::Error),
+            ("Err", log::LevelFilter::Error),
+            ("information", log::LevelFilter::Info),
+            ("INFORMATION", log::LevelFilter::Info),
+        ] {
+            let filter = parse(alias).unwrap();
+            assert_eq!(filter.level_global, Some(level), "global level {alias}");
+
+            let filter = parse(&format!("my_module={alias}")).unwrap();
+            assert_eq!(
+                filter.directive_levels,
+                vec![level],
+                "directive level {alias}"
+            );
+        }
+    }
+
+    #[test]
+    fn err_reports_offending_span() {
+        let input = "info,project=trce";
+        let err = parse(input).err().unwrap();
+        assert_eq!(err.kind, ParseErrorKind::InvalidLevel);
+        assert_eq!(err.span, "trce");
+        assert_eq!(err.offset, 13);
+        assert_eq!(&input[err.offset..][..err.span.len()], err.span);
+        assert!(
+            err.to_string()
+                .starts_with("invalid level 'trce' at position 13"),
+            "{err}"
+        );
+
+        let input = "editor=debug, project = trce ,agent=info";
+        let err = parse(input).err().unwrap();
+        assert_eq!(err.span, "trce");
+        assert_eq!(err.offset, 24);
+
+        let input = "info,editor=debug,warn";
+        let err = parse(input).err().unwrap();
+        assert_eq!(err.kind, ParseErrorKind::MultipleMaxLevels);
+        assert_eq!(err.span, "warn");
+        assert_eq!(err.offset, 18);
+
+        let input = "info,editor=debug=trace";
+        let err = parse(input).err().unwrap();
+        assert_eq!(err.kind, ParseErrorKind::InvalidDirective);
+        assert_eq!(err.span, "editor=debug=trace");
+        assert_eq!(err.offset, 5);
+    }
+
+    #[test]
+    fn output_target() {
+        assert_eq!(parse_output("stdout"), Some(OutputTarget::Stdout));
+        assert_eq!(parse_output(" STDERR "), Some(OutputTarget::Stderr));
+        assert_eq!(
+            parse_output("file:/tmp/zed.log"),
+            Some(OutputTarget::File(PathBuf::from("/tmp/zed.log")))
+        );
+        assert_eq!(parse_output("file:"), None);
+        assert_eq!(parse_output("syslog"), None);
+    }
+
     #[test]
     fn err_when_invalid_level() {
         let input = "my_module=foobar";
         let result = parse(input);
 
-        assert!(result.is_err());
+        let err = result.err().unwrap().to_string();
+        assert!(err.contains("foobar"), "{err}");
+        assert!(
+            err.contains("trace, debug, info, warn, error, off"),
+            "{err}"
+        );
     }
 }
@@ -1,18 +1,37 @@
 use collections::HashMap;
-use std::collections::VecDeque;
-use std::sync::{
-    OnceLock, RwLock,
-    atomic::{AtomicU8, Ordering},
+use std::{
+    cell::Cell,
+    sync::{
+        LazyLock, Mutex, RwLock,
+        atomic::{AtomicBool, AtomicU8, AtomicU64, AtomicUsize, Ordering},
+    },
 };
 
-use crate::{SCOPE_DEPTH_MAX, SCOPE_STRING_SEP_STR, ScopeAlloc, ScopeRef, env_config, private};
+use crate::{
+    SCOPE_DEPTH_MAX, SCOPE_STRING_SEP_STR, ScopeAlloc, ScopeRef, env_config, filter_core, private,
+};
+
+use crate::filter_core::level_filter_from_str;
+pub use crate::filter_core::{EnabledStatus, ScopeMap, ScopeMapEntry};
 
 use log;
+use serde::Serialize;
 
-static ENV_FILTER: OnceLock<env_config::EnvFilter> = OnceLock::new();
+static ENV_FILTER: RwLock<Option<env_config::EnvFilter>> = RwLock::new(None);
 static SCOPE_MAP: RwLock<ScopeMap> = RwLock::new(ScopeMap::empty());
 
 pub const LEVEL_ENABLED_MAX_DEFAULT: log::LevelFilter = log::LevelFilter::Info;
+/// The most verbose level that the logging macros can emit at all. Records more
+/// verbose than this are compiled out, including their argument expressions.
+pub const LEVEL_ENABLED_MAX_COMPILE_TIME: log::LevelFilter = if cfg!(all(
+    feature = "release_max_level_info",
+    not(debug_assertions)
+)) {
+    log::LevelFilter::Info
+} else {
+    log::LevelFilter::Trace
+};
+
 /// The maximum log level of verbosity that is enabled by default.
 /// All messages more verbose than this level will be discarded
 /// by default unless specially configured.
@@ -45,17 +64,264 @@ const DEFAULT_FILTERS: &[(&str, log::LevelFilter)] = &[
     ("pet", log::LevelFilter::Warn),
 ];
 
+/// Sets the filter parsed from `ZED_LOG`, replacing any previous one. It takes
+/// effect on the next `refresh_from_settings`.
 pub fn init_env_filter(filter: env_config::EnvFilter) {
-    if let Some(level_max) = filter.level_global {
+    if let Some(level_max) = filter.level_default() {
         LEVEL_ENABLED_MAX_STATIC.store(level_max as u8, Ordering::Release)
     }
-    if ENV_FILTER.set(filter).is_err() {
-        panic!("Environment filter cannot be initialized twice");
-    }
+    set_sampling(&filter.sampling);
+    *ENV_FILTER.write().unwrap_or_else(|err| {
+        ENV_FILTER.clear_poison();
+        err.into_inner()
+    }) = Some(filter);
+}
+
+/// Is set if all logging was disabled with `set_disabled`.
+static DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Disables all logging, regardless of the filter configuration, so that every
+/// record is rejected by the `is_possibly_enabled_level` fast path.
+pub fn set_disabled(disabled: bool) {
+    DISABLED.store(disabled, Ordering::Release);
+    let level_enabled_max = if disabled {
+        log::LevelFilter::Off as u8
+    } else {
+        let global_scope_map = SCOPE_MAP.read().unwrap_or_else(|err| {
+            SCOPE_MAP.clear_poison();
+            err.into_inner()
+        });
+        level_enabled_max(
+            LEVEL_ENABLED_MAX_STATIC.load(Ordering::Acquire),
+            &global_scope_map,
+        )
+    };
+    LEVEL_ENABLED_MAX_CONFIG.store(level_enabled_max, Ordering::Release);
+}
+
+pub fn is_disabled() -> bool {
+    DISABLED.load(Ordering::Acquire)
 }
 
 pub fn is_possibly_enabled_level(level: log::Level) -> bool {
     level as u8 <= LEVEL_ENABLED_MAX_CONFIG.load(Ordering::Acquire)
+        || level_override().is_some_and(|level_override| level <= level_override)
+}
+
+/// Scopes muted with `mute_scope`, along with all of their subscopes.
+static MUTED_SCOPES: RwLock<Vec<Vec<String>>> = RwLock::new(Vec::new());
+/// Whether `MUTED_SCOPES` is non-empty, so that it's free to check when nothing
+/// is muted.
+static ANY_MUTED: AtomicBool = AtomicBool::new(false);
+
+/// Suppresses every record in `scope` and its subscopes, regardless of the
+/// configured levels, until `unmute_scope` is called with the same scope.
+pub fn mute_scope(scope: &[&str]) {
+    let scope = scope
+        .iter()
+        .take(SCOPE_DEPTH_MAX)
+        .map(|subscope| subscope.to_string())
+        .collect::<Vec<_>>();
+    if scope.is_empty() {
+        return;
+    }
+    let mut muted = MUTED_SCOPES.write().unwrap_or_else(|err| {
+        MUTED_SCOPES.clear_poison();
+        err.into_inner()
+    });
+    if !muted.contains(&scope) {
+        muted.push(scope);
+    }
+    ANY_MUTED.store(true, Ordering::Release);
+}
+
+/// Reverts a previous `mute_scope` call for `scope`.
+pub fn unmute_scope(scope: &[&str]) {
+    let mut muted = MUTED_SCOPES.write().unwrap_or_else(|err| {
+        MUTED_SCOPES.clear_poison();
+        err.into_inner()
+    });
+    muted.retain(|muted_scope| !muted_scope.iter().eq(scope.iter().take(SCOPE_DEPTH_MAX)));
+    ANY_MUTED.store(!muted.is_empty(), Ordering::Release);
+}
+
+fn is_scope_muted(scope: &ScopeRef<'_>, module_path: Option<&str>) -> bool {
+    let mut crate_name_scope = [""; SCOPE_DEPTH_MAX];
+    let scope = if scope[0].is_empty() {
+        let Some(module_path) = module_path else {
+            return false;
+        };
+        crate_name_scope[0] = private::extract_crate_name_from_module_path(module_path);
+        &crate_name_scope
+    } else {
+        scope
+    };
+    let muted = MUTED_SCOPES.read().unwrap_or_else(|err| {
+        MUTED_SCOPES.clear_poison();
+        err.into_inner()
+    });
+    muted.iter().any(|muted_scope| {
+        muted_scope
+            .iter()
+            .zip(scope)
+            .all(|(muted, subscope)| muted == subscope)
+    })
+}
+
+/// Whether `is_scope_enabled` counts the records it suppresses.
+static COUNT_SUPPRESSED: AtomicBool = AtomicBool::new(false);
+/// Number of suppressed records per scope, if `COUNT_SUPPRESSED` is set.
+static SUPPRESSED_COUNTS: LazyLock<Mutex<HashMap<String, u64>>> =
+    LazyLock::new(|| Mutex::new(HashMap::default()));
+
+/// Enables or disables counting the records suppressed by the filter in each
+/// scope. Counting is disabled by default.
+pub fn set_count_suppressed(enabled: bool) {
+    COUNT_SUPPRESSED.store(enabled, Ordering::Release);
+}
+
+/// Number of records suppressed by the filter per scope while counting was
+/// enabled. Records logged without a scope are counted under their crate name.
+pub fn suppressed_counts() -> HashMap<String, u64> {
+    SUPPRESSED_COUNTS
+        .lock()
+        .unwrap_or_else(|err| {
+            SUPPRESSED_COUNTS.clear_poison();
+            err.into_inner()
+        })
+        .clone()
+}
+
+fn count_suppressed(scope: &ScopeRef<'_>, module_path: Option<&str>) {
+    let key = if scope[0].is_empty() {
+        module_path
+            .map(private::extract_crate_name_from_module_path)
+            .unwrap_or("?")
+            .to_string()
+    } else {
+        scope
+            .iter()
+            .take_while(|subscope| !subscope.is_empty())
+            .copied()
+            .collect::<Vec<_>>()
+            .join(SCOPE_STRING_SEP_STR)
+    };
+    let mut counts = SUPPRESSED_COUNTS.lock().unwrap_or_else(|err| {
+        SUPPRESSED_COUNTS.clear_poison();
+        err.into_inner()
+    });
+    *counts.entry(key).or_default() += 1;
+}
+
+/// Key-value pairs that a record's context must contain one of, see
+/// `add_field_filter`.
+static FIELD_FILTERS: RwLock<Vec<(String, String)>> = RwLock::new(Vec::new());
+/// Whether `FIELD_FILTERS` is non-empty, so that it's free to check when no
+/// field filters are registered.
+static ANY_FIELD_FILTERS: AtomicBool = AtomicBool::new(false);
+
+/// Only emits records whose context (see `zlog::context`) has `key` set to
+/// `value`, e.g. to follow a single request. If several field filters are
+/// registered, records matching any of them are emitted. Scope and level
+/// filtering still applies to the records that match.
+pub fn add_field_filter(key: impl Into<String>, value: impl Into<String>) {
+    let filter = (key.into(), value.into());
+    let mut field_filters = FIELD_FILTERS.write().unwrap_or_else(|err| {
+        FIELD_FILTERS.clear_poison();
+        err.into_inner()
+    });
+    if !field_filters.contains(&filter) {
+        field_filters.push(filter);
+    }
+    ANY_FIELD_FILTERS.store(true, Ordering::Release);
+}
+
+/// Removes every field filter, so records are emitted regardless of their
+/// context again.
+pub fn clear_field_filters() {
+    let mut field_filters = FIELD_FILTERS.write().unwrap_or_else(|err| {
+        FIELD_FILTERS.clear_poison();
+        err.into_inner()
+    });
+    field_filters.clear();
+    ANY_FIELD_FILTERS.store(false, Ordering::Release);
+}
+
+fn matches_field_filters() -> bool {
+    let field_filters = FIELD_FILTERS.read().unwrap_or_else(|err| {
+        FIELD_FILTERS.clear_poison();
+        err.into_inner()
+    });
+    crate::context::with_current(|context| {
+        field_filters.iter().any(|(filter_key, filter_value)| {
+            context
+                .iter()
+                .any(|(key, value)| key == filter_key && value == filter_value)
+        })
+    })
+}
+
+/// Scopes and modules whose records are sampled, along with the number of
+/// their records that were otherwise enabled so far.
+static SAMPLING: RwLock<Vec<(String, env_config::Sampling, AtomicU64)>> = RwLock::new(Vec::new());
+/// Whether `SAMPLING` is non-empty, so that it's free to check when nothing is
+/// sampled.
+static ANY_SAMPLING: AtomicBool = AtomicBool::new(false);
+
+fn set_sampling(sampling: &[(String, env_config::Sampling)]) {
+    let mut sampled = SAMPLING.write().unwrap_or_else(|err| {
+        SAMPLING.clear_poison();
+        err.into_inner()
+    });
+    *sampled = sampling
+        .iter()
+        .map(|(name, sampling)| (name.clone(), *sampling, AtomicU64::new(0)))
+        .collect();
+    ANY_SAMPLING.store(!sampled.is_empty(), Ordering::Release);
+}
+
+/// Whether a record that passed `is_scope_enabled` is emitted by the most
+/// specific sampling directive that applies to it, if any. Directives naming a
+/// module apply to its submodules, and directives naming a scope to its
+/// subscopes. Of equally specific directives, the last one wins.
+///
+/// Each call counts towards the sample, so it's made once per record, right
+/// before the record is submitted, rather than by checks such as
+/// `Logger::is_enabled` that don't log.
+pub fn is_sampled(scope: &ScopeRef<'_>, module_path: Option<&str>) -> bool {
+    if !ANY_SAMPLING.load(Ordering::Acquire) {
+        return true;
+    }
+    let sampling = SAMPLING.read().unwrap_or_else(|err| {
+        SAMPLING.clear_poison();
+        err.into_inner()
+    });
+    let specificity = |name: &str| {
+        if name.contains("::") {
+            let module_path = module_path?;
+            let matches = module_path
+                .strip_prefix(name)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"));
+            return matches.then(|| name.split("::").count());
+        }
+        let mut depth = 0;
+        for subscope in name.split(SCOPE_STRING_SEP_STR) {
+            if scope.get(depth) != Some(&subscope) {
+                return None;
+            }
+            depth += 1;
+        }
+        Some(depth)
+    };
+    let Some((_, sampling, count)) = sampling
+        .iter()
+        .filter_map(|entry| Some((specificity(&entry.0)?, entry)))
+        .max_by_key(|(specificity, _)| *specificity)
+        .map(|(_, entry)| entry)
+    else {
+        return true;
+    };
+    count.fetch_add(1, Ordering::Relaxed) % u64::from(sampling.m) < u64::from(sampling.n)
 }
 
 pub fn is_scope_enabled(
@@ -63,6 +329,31 @@ pub fn is_scope_enabled(
     module_path: Option<&str>,
     level: log::Level,
 ) -> bool {
+    let enabled = is_scope_enabled_uncounted(scope, module_path, level);
+    if !enabled && COUNT_SUPPRESSED.load(Ordering::Relaxed) {
+        count_suppressed(scope, module_path);
+    }
+    enabled && (!ANY_FIELD_FILTERS.load(Ordering::Acquire) || matches_field_filters())
+}
+
+/// Like `is_scope_enabled`, for scopes made of owned strings, such as scopes
+/// that are built at runtime.
+pub fn is_scope_alloc_enabled(
+    scope: &ScopeAlloc,
+    module_path: Option<&str>,
+    level: log::Level,
+) -> bool {
+    is_scope_enabled(&scope.each_ref().map(String::as_str), module_path, level)
+}
+
+fn is_scope_enabled_uncounted(
+    scope: &ScopeRef<'_>,
+    module_path: Option<&str>,
+    level: log::Level,
+) -> bool {
+    if ANY_MUTED.load(Ordering::Acquire) && is_scope_muted(scope, module_path) {
+        return false;
+    }
     // TODO: is_always_allowed_level that checks against LEVEL_ENABLED_MIN_CONFIG
     if !is_possibly_enabled_level(level) {
         // [FAST PATH]
@@ -71,33 +362,103 @@ pub fn is_scope_enabled(
         // scope map
         return false;
     }
-    let is_enabled_by_default = level as u8 <= LEVEL_ENABLED_MAX_STATIC.load(Ordering::Acquire);
     let global_scope_map = SCOPE_MAP.read().unwrap_or_else(|err| {
         SCOPE_MAP.clear_poison();
         err.into_inner()
     });
+    filter_core::is_scope_enabled(
+        &global_scope_map,
+        level_override().unwrap_or_else(level_enabled_max_static),
+        scope,
+        module_path,
+        level,
+    )
+}
 
-    if global_scope_map.is_empty() {
-        // if no scopes are enabled, return false because it's not <= LEVEL_ENABLED_MAX_STATIC
-        return is_enabled_by_default;
-    }
-    let enabled_status = global_scope_map.is_enabled(scope, module_path, level);
-    match enabled_status {
-        EnabledStatus::NotConfigured => is_enabled_by_default,
-        EnabledStatus::Enabled => true,
-        EnabledStatus::Disabled => false,
-    }
+/// The most verbose level enabled by `level_default` or any scope in
+/// `scope_map`, for `LEVEL_ENABLED_MAX_CONFIG`.
+fn level_enabled_max(level_default: u8, scope_map: &ScopeMap) -> u8 {
+    scope_map
+        .entries
+        .iter()
+        .filter_map(|entry| entry.enabled)
+        .fold(level_default, |level_max, level| level_max.max(level as u8))
+}
+
+fn level_enabled_max_static() -> log::LevelFilter {
+    let level = LEVEL_ENABLED_MAX_STATIC.load(Ordering::Acquire);
+    log::LevelFilter::iter()
+        .find(|level_filter| *level_filter as u8 == level)
+        .unwrap_or(LEVEL_ENABLED_MAX_DEFAULT)
 }
 
 pub fn refresh_from_settings(settings: &HashMap<String, String>) {
-    let env_config = ENV_FILTER.get();
-    let map_new = ScopeMap::new_from_settings_and_env(settings, env_config, DEFAULT_FILTERS);
-    let mut level_enabled_max = LEVEL_ENABLED_MAX_STATIC.load(Ordering::Acquire);
-    for entry in &map_new.entries {
-        if let Some(level) = entry.enabled {
-            level_enabled_max = level_enabled_max.max(level as u8);
-        }
+    refresh_from_settings_with_default_level(None, settings);
+}
+
+/// The arguments of the last `refresh_from_settings_with_default_level` call,
+/// which `reset` re-applies.
+static LAST_SETTINGS: RwLock<Option<(Option<String>, HashMap<String, String>)>> = RwLock::new(None);
+
+/// Restores the filter derived from `ZED_LOG` and settings: clears muted
+/// scopes and field filters, and re-applies the last settings passed to
+/// `refresh_from_settings`.
+pub fn reset() {
+    {
+        let mut muted = MUTED_SCOPES.write().unwrap_or_else(|err| {
+            MUTED_SCOPES.clear_poison();
+            err.into_inner()
+        });
+        muted.clear();
+        ANY_MUTED.store(false, Ordering::Release);
     }
+    clear_field_filters();
+    let (default_level, settings) = LAST_SETTINGS
+        .read()
+        .unwrap_or_else(|err| {
+            LAST_SETTINGS.clear_poison();
+            err.into_inner()
+        })
+        .clone()
+        .unwrap_or_default();
+    refresh_from_settings_with_default_level(default_level.as_deref(), &settings);
+}
+
+/// The filter last logged by `refresh_from_settings_with_default_level`, in
+/// `ZED_LOG` syntax, so that a refresh that doesn't change it isn't logged.
+static LAST_APPLIED_FILTER: Mutex<String> = Mutex::new(String::new());
+
+/// Like `refresh_from_settings`, but also sets the level of scopes that aren't
+/// configured. A `*` directive or global level set through `ZED_LOG` takes
+/// precedence over `default_level`, and `LEVEL_ENABLED_MAX_DEFAULT` is used if
+/// none is set.
+pub fn refresh_from_settings_with_default_level(
+    default_level: Option<&str>,
+    settings: &HashMap<String, String>,
+) {
+    *LAST_SETTINGS.write().unwrap_or_else(|err| {
+        LAST_SETTINGS.clear_poison();
+        err.into_inner()
+    }) = Some((default_level.map(ToString::to_string), settings.clone()));
+    let env_config = ENV_FILTER.read().unwrap_or_else(|err| {
+        ENV_FILTER.clear_poison();
+        err.into_inner()
+    });
+    let level_default = env_config
+        .as_ref()
+        .and_then(|env_config| env_config.level_default())
+        .or_else(|| default_level.and_then(level_filter_from_str))
+        .unwrap_or(LEVEL_ENABLED_MAX_DEFAULT);
+    LEVEL_ENABLED_MAX_STATIC.store(level_default as u8, Ordering::Release);
+
+    let map_new =
+        ScopeMap::new_from_settings_and_env(settings, env_config.as_ref(), DEFAULT_FILTERS);
+    drop(env_config);
+    let level_enabled_max = if is_disabled() {
+        log::LevelFilter::Off as u8
+    } else {
+        level_enabled_max(level_default as u8, &map_new)
+    };
     LEVEL_ENABLED_MAX_CONFIG.store(level_enabled_max, Ordering::Release);
 
     {
@@ -107,300 +468,224 @@ pub fn refresh_from_settings(settings: &HashMap<String, String>) {
         });
         *global_map = map_new;
     }
-    log::trace!("Log configuration updated");
-}
-
-fn level_filter_from_str(level_str: &str) -> Option<log::LevelFilter> {
-    use log::LevelFilter::*;
-    let level = match level_str.to_ascii_lowercase().as_str() {
-        "" => Trace,
-        "trace" => Trace,
-        "debug" => Debug,
-        "info" => Info,
-        "warn" => Warn,
-        "error" => Error,
-        "off" => Off,
-        "disable" | "no" | "none" | "disabled" => {
-            crate::warn!(
-                "Invalid log level \"{level_str}\", to disable logging set to \"off\". Defaulting to \"off\"."
-            );
-            Off
-        }
-        _ => {
-            crate::warn!("Invalid log level \"{level_str}\", ignoring");
-            return None;
+    // Logged after the scope map lock is released, since logging reads it.
+    let applied = to_env_string();
+    let changed = {
+        let mut last_applied = LAST_APPLIED_FILTER.lock().unwrap_or_else(|err| {
+            LAST_APPLIED_FILTER.clear_poison();
+            err.into_inner()
+        });
+        if *last_applied == applied {
+            false
+        } else {
+            last_applied.clone_from(&applied);
+            true
         }
     };
-    Some(level)
+    if changed {
+        crate::info!(crate::scoped!("filter") => "Log filter applied: {applied}");
+    }
 }
 
-fn scope_alloc_from_scope_str(scope_str: &str) -> Option<ScopeAlloc> {
-    let mut scope_buf = [""; SCOPE_DEPTH_MAX];
-    let mut index = 0;
-    let mut scope_iter = scope_str.split(SCOPE_STRING_SEP_STR);
-    while index < SCOPE_DEPTH_MAX {
-        let Some(scope) = scope_iter.next() else {
-            break;
-        };
-        if scope.is_empty() {
-            continue;
-        }
-        scope_buf[index] = scope;
-        index += 1;
-    }
-    if index == 0 {
-        return None;
-    }
-    if scope_iter.next().is_some() {
-        crate::warn!(
-            "Invalid scope key, too many nested scopes: '{scope_str}'. Max depth is {SCOPE_DEPTH_MAX}",
-        );
+thread_local! {
+    /// The level set by the innermost `with_level` block running on this thread.
+    static LEVEL_OVERRIDE: Cell<Option<log::LevelFilter>> = const { Cell::new(None) };
+}
+
+/// The number of `with_level` blocks running on any thread, so that checking
+/// for an override is free when there are none.
+static LEVEL_OVERRIDES_ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+/// The level set by `with_level` on this thread, unless logging is disabled.
+fn level_override() -> Option<log::LevelFilter> {
+    if LEVEL_OVERRIDES_ACTIVE.load(Ordering::Acquire) == 0 || is_disabled() {
         return None;
     }
-    let scope = scope_buf.map(|s| s.to_string());
-    Some(scope)
+    LEVEL_OVERRIDE.try_with(Cell::get).ok().flatten()
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub struct ScopeMap {
-    entries: Vec<ScopeMapEntry>,
-    modules: Vec<(String, log::LevelFilter)>,
-    root_count: usize,
+/// Runs `f` with `level` as the level of scopes that aren't configured on the
+/// current thread, then restores the previous level, including if `f` panics.
+/// Calls may be nested. Other threads and the filter configuration, which may
+/// be refreshed while `f` runs, are unaffected, and nothing is logged while
+/// logging is disabled with `set_disabled`.
+pub fn with_level<R>(level: log::LevelFilter, f: impl FnOnce() -> R) -> R {
+    let _guard = LevelGuard {
+        previous: LEVEL_OVERRIDE.replace(Some(level)),
+    };
+    LEVEL_OVERRIDES_ACTIVE.fetch_add(1, Ordering::AcqRel);
+    f()
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub struct ScopeMapEntry {
-    scope: String,
-    enabled: Option<log::LevelFilter>,
-    descendants: std::ops::Range<usize>,
+struct LevelGuard {
+    previous: Option<log::LevelFilter>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum EnabledStatus {
-    Enabled,
-    Disabled,
-    NotConfigured,
+impl Drop for LevelGuard {
+    fn drop(&mut self) {
+        LEVEL_OVERRIDES_ACTIVE.fetch_sub(1, Ordering::AcqRel);
+        LEVEL_OVERRIDE.set(self.previous);
+    }
 }
 
-impl ScopeMap {
-    pub fn new_from_settings_and_env(
-        items_input_map: &HashMap<String, String>,
-        env_config: Option<&env_config::EnvFilter>,
-        default_filters: &[(&str, log::LevelFilter)],
-    ) -> Self {
-        let mut items = Vec::<(ScopeAlloc, log::LevelFilter)>::with_capacity(
-            items_input_map.len()
-                + env_config.map_or(0, |c| c.directive_names.len())
-                + default_filters.len(),
-        );
-        let mut modules = Vec::with_capacity(4);
-
-        let env_filters = env_config.iter().flat_map(|env_filter| {
-            env_filter
-                .directive_names
-                .iter()
-                .zip(env_filter.directive_levels.iter())
-                .map(|(scope_str, level_filter)| (scope_str.as_str(), *level_filter))
-        });
-
-        let new_filters = items_input_map.iter().filter_map(|(scope_str, level_str)| {
-            let level_filter = level_filter_from_str(level_str)?;
-            Some((scope_str.as_str(), level_filter))
-        });
-
-        let all_filters = default_filters
-            .iter()
-            .cloned()
-            .chain(env_filters)
-            .chain(new_filters);
-
-        for (scope_str, level_filter) in all_filters {
-            if scope_str.contains("::") {
-                if let Some(idx) = modules.iter().position(|(module, _)| module == scope_str) {
-                    modules[idx].1 = level_filter;
-                } else {
-                    modules.push((scope_str.to_string(), level_filter));
-                }
-                continue;
-            }
-            let Some(scope) = scope_alloc_from_scope_str(scope_str) else {
-                continue;
-            };
-            if let Some(idx) = items
-                .iter()
-                .position(|(scope_existing, _)| scope_existing == &scope)
-            {
-                items[idx].1 = level_filter;
-            } else {
-                items.push((scope, level_filter));
-            }
-        }
-
-        items.sort_by(|a, b| a.0.cmp(&b.0));
-        modules.sort_by(|(a_name, _), (b_name, _)| a_name.cmp(b_name));
-
-        let mut this = Self {
-            entries: Vec::with_capacity(items.len() * SCOPE_DEPTH_MAX),
-            modules,
-            root_count: 0,
-        };
+/// A filter table that is isolated from the global one, for embedded subsystems
+/// that shouldn't share filter configuration with the rest of the process.
+///
+/// Records are routed through a namespace with [`Namespace::submit`], which
+/// consults only the namespace's own scope map and default level, skipping the
+/// global filter (including `ZED_LOG`, settings, and muted scopes). Records it
+/// enables are written to the same sinks as all other records.
+pub struct Namespace {
+    name: &'static str,
+    scope_map: RwLock<ScopeMap>,
+    level_default: AtomicU8,
+}
 
-        let items_count = items.len();
+static NAMESPACES: Mutex<Vec<&'static Namespace>> = Mutex::new(Vec::new());
 
-        struct ProcessQueueEntry {
-            parent_index: usize,
-            depth: usize,
-            items_range: std::ops::Range<usize>,
-        }
-        let mut process_queue = VecDeque::new();
-        process_queue.push_back(ProcessQueueEntry {
-            parent_index: usize::MAX,
-            depth: 0,
-            items_range: 0..items_count,
-        });
+/// Returns the namespace called `name`, creating it with no configured scopes
+/// and a default level of `LEVEL_ENABLED_MAX_DEFAULT` on first use.
+pub fn namespace(name: &'static str) -> &'static Namespace {
+    let mut namespaces = NAMESPACES.lock().unwrap_or_else(|err| {
+        NAMESPACES.clear_poison();
+        err.into_inner()
+    });
+    if let Some(namespace) = namespaces.iter().find(|namespace| namespace.name == name) {
+        return namespace;
+    }
+    let namespace = Box::leak(Box::new(Namespace {
+        name,
+        scope_map: RwLock::new(ScopeMap::empty()),
+        level_default: AtomicU8::new(LEVEL_ENABLED_MAX_DEFAULT as u8),
+    }));
+    namespaces.push(namespace);
+    namespace
+}
 
-        let empty_range = 0..0;
-
-        while let Some(process_entry) = process_queue.pop_front() {
-            let ProcessQueueEntry {
-                items_range,
-                depth,
-                parent_index,
-            } = process_entry;
-            let mut cursor = items_range.start;
-            let res_entries_start = this.entries.len();
-            while cursor < items_range.end {
-                let sub_items_start = cursor;
-                cursor += 1;
-                let scope_name = &items[sub_items_start].0[depth];
-                while cursor < items_range.end && &items[cursor].0[depth] == scope_name {
-                    cursor += 1;
-                }
-                let sub_items_end = cursor;
-                if scope_name.is_empty() {
-                    assert_eq!(sub_items_start + 1, sub_items_end);
-                    assert_ne!(depth, 0);
-                    assert_ne!(parent_index, usize::MAX);
-                    assert!(this.entries[parent_index].enabled.is_none());
-                    this.entries[parent_index].enabled = Some(items[sub_items_start].1);
-                    continue;
-                }
-                let is_valid_scope = !scope_name.is_empty();
-                let is_last = depth + 1 == SCOPE_DEPTH_MAX || !is_valid_scope;
-                let mut enabled = None;
-                if is_last {
-                    assert_eq!(
-                        sub_items_start + 1,
-                        sub_items_end,
-                        "Expected one item: got: {:?}",
-                        &items[items_range]
-                    );
-                    enabled = Some(items[sub_items_start].1);
-                } else {
-                    let entry_index = this.entries.len();
-                    process_queue.push_back(ProcessQueueEntry {
-                        items_range: sub_items_start..sub_items_end,
-                        parent_index: entry_index,
-                        depth: depth + 1,
-                    });
-                }
-                this.entries.push(ScopeMapEntry {
-                    scope: scope_name.to_owned(),
-                    enabled,
-                    descendants: empty_range.clone(),
-                });
-            }
-            let res_entries_end = this.entries.len();
-            if parent_index != usize::MAX {
-                this.entries[parent_index].descendants = res_entries_start..res_entries_end;
-            } else {
-                this.root_count = res_entries_end;
-            }
-        }
+impl Namespace {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
 
-        this
+    /// Sets the level of scopes that aren't configured in this namespace.
+    pub fn set_default_level(&self, level: log::LevelFilter) {
+        self.level_default.store(level as u8, Ordering::Release);
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.entries.is_empty() && self.modules.is_empty()
+    /// Replaces this namespace's scope map with `settings`, in the same format
+    /// as `refresh_from_settings`.
+    pub fn refresh_from_settings(&self, settings: &HashMap<String, String>) {
+        let map_new = ScopeMap::new_from_settings_and_env(settings, None, &[]);
+        *self.scope_map.write().unwrap_or_else(|err| {
+            self.scope_map.clear_poison();
+            err.into_inner()
+        }) = map_new;
     }
 
-    pub fn is_enabled<S>(
+    pub fn is_scope_enabled(
         &self,
-        scope: &[S; SCOPE_DEPTH_MAX],
+        scope: &ScopeRef<'_>,
         module_path: Option<&str>,
         level: log::Level,
-    ) -> EnabledStatus
-    where
-        S: AsRef<str>,
-    {
-        fn search<S>(map: &ScopeMap, scope: &[S; SCOPE_DEPTH_MAX]) -> Option<log::LevelFilter>
-        where
-            S: AsRef<str>,
-        {
-            let mut enabled = None;
-            let mut cur_range = &map.entries[0..map.root_count];
-            let mut depth = 0;
-            'search: while !cur_range.is_empty()
-                && depth < SCOPE_DEPTH_MAX
-                && scope[depth].as_ref() != ""
-            {
-                for entry in cur_range {
-                    if entry.scope == scope[depth].as_ref() {
-                        enabled = entry.enabled.or(enabled);
-                        cur_range = &map.entries[entry.descendants.clone()];
-                        depth += 1;
-                        continue 'search;
-                    }
-                }
-                break 'search;
-            }
-            enabled
+    ) -> bool {
+        let is_enabled_by_default = level as u8 <= self.level_default.load(Ordering::Acquire);
+        let scope_map = self.scope_map.read().unwrap_or_else(|err| {
+            self.scope_map.clear_poison();
+            err.into_inner()
+        });
+        if scope_map.is_empty() {
+            return is_enabled_by_default;
+        }
+        match scope_map.is_enabled(scope, module_path, level) {
+            EnabledStatus::NotConfigured => is_enabled_by_default,
+            EnabledStatus::Enabled => true,
+            EnabledStatus::Disabled => false,
         }
+    }
 
-        let mut enabled = search(self, scope);
+    /// Writes `record` to the sinks if this namespace enables it.
+    pub fn submit(&self, record: crate::sink::Record) {
+        if self.is_scope_enabled(&record.scope, record.module_path, record.level) {
+            crate::sink::submit(record);
+        }
+    }
+}
 
-        if let Some(module_path) = module_path {
-            let scope_is_empty = scope[0].as_ref().is_empty();
+/// Held by tests that apply settings with `refresh_from_settings`, since they
+/// would otherwise overwrite each other's configuration.
+#[cfg(test)]
+pub(crate) static REFRESH_TEST_LOCK: Mutex<()> = Mutex::new(());
 
-            if enabled.is_none() && scope_is_empty {
-                let crate_name = private::extract_crate_name_from_module_path(module_path);
-                let mut crate_name_scope = [""; SCOPE_DEPTH_MAX];
-                crate_name_scope[0] = crate_name;
-                enabled = search(self, &crate_name_scope);
-            }
+/// Renders the live filter configuration, including scopes configured in
+/// settings, in `ZED_LOG` syntax.
+pub fn to_env_string() -> String {
+    let level_global = LEVEL_ENABLED_MAX_STATIC.load(Ordering::Acquire);
+    let level_global = log::LevelFilter::iter().find(|level| *level as u8 == level_global);
+    let global_scope_map = SCOPE_MAP.read().unwrap_or_else(|err| {
+        SCOPE_MAP.clear_poison();
+        err.into_inner()
+    });
+    global_scope_map.to_env_filter(level_global).to_env_string()
+}
 
-            if !self.modules.is_empty() {
-                let crate_name = private::extract_crate_name_from_module_path(module_path);
-                let is_scope_just_crate_name =
-                    scope[0].as_ref() == crate_name && scope[1].as_ref() == "";
-                if enabled.is_none() || is_scope_just_crate_name {
-                    for (module, filter) in &self.modules {
-                        if module == module_path {
-                            enabled.replace(*filter);
-                            break;
-                        }
-                    }
-                }
-            }
-        }
+/// The live filter configuration, for dumping into bug reports.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FilterSnapshot {
+    /// The level of scopes that aren't configured.
+    pub default_level: log::LevelFilter,
+    /// Whether all logging is disabled, see `set_disabled`.
+    pub disabled: bool,
+    /// Scopes and modules configured with a level other than `off`.
+    pub scopes: Vec<ScopeLevel>,
+    /// Scopes and modules configured as `off`.
+    pub excluded: Vec<String>,
+    /// Scopes muted with `mute_scope`.
+    pub muted: Vec<String>,
+}
 
-        if let Some(enabled_filter) = enabled {
-            if level <= enabled_filter {
-                return EnabledStatus::Enabled;
-            }
-            return EnabledStatus::Disabled;
-        }
-        EnabledStatus::NotConfigured
-    }
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScopeLevel {
+    pub scope: String,
+    pub level: log::LevelFilter,
+}
 
-    const fn empty() -> ScopeMap {
-        ScopeMap {
-            entries: vec![],
-            modules: vec![],
-            root_count: 0,
+/// Captures the live filter configuration. It's read while holding the scope
+/// map lock, so it never mixes two applications of the settings.
+pub fn snapshot() -> FilterSnapshot {
+    let global_scope_map = SCOPE_MAP.read().unwrap_or_else(|err| {
+        SCOPE_MAP.clear_poison();
+        err.into_inner()
+    });
+    let muted = MUTED_SCOPES.read().unwrap_or_else(|err| {
+        MUTED_SCOPES.clear_poison();
+        err.into_inner()
+    });
+    let default_level = level_enabled_max_static();
+    let env_filter = global_scope_map.to_env_filter(None);
+
+    let mut scopes = Vec::new();
+    let mut excluded = Vec::new();
+    for (scope, level) in env_filter
+        .directive_names
+        .into_iter()
+        .zip(env_filter.directive_levels)
+    {
+        if level == log::LevelFilter::Off {
+            excluded.push(scope);
+        } else {
+            scopes.push(ScopeLevel { scope, level });
         }
     }
+    FilterSnapshot {
+        default_level,
+        disabled: is_disabled(),
+        scopes,
+        excluded,
+        muted: muted
+            .iter()
+            .map(|scope| scope.join(SCOPE_STRING_SEP_STR))
+            .collect(),
+    }
 }
 
 #[cfg(test)]
@@ -541,6 +826,85 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_directive_matches_nested_scopes_and_modules() {
+        use log::Level;
+        let map = ScopeMap::new_from_settings_and_env(
+            [
+                ("editor", "debug"),
+                ("editor.buffer", "warn"),
+                ("editor::display_map", "trace"),
+            ],
+            None,
+            &[],
+        );
+        for (scope, module_path, level, expected) in [
+            // the crate directive covers every scope and module in the crate
+            (
+                "editor",
+                Some("editor"),
+                Level::Debug,
+                EnabledStatus::Enabled,
+            ),
+            (
+                "editor",
+                Some("editor::buffer"),
+                Level::Debug,
+                EnabledStatus::Enabled,
+            ),
+            (
+                "editor",
+                Some("editor::buffer::sync"),
+                Level::Debug,
+                EnabledStatus::Enabled,
+            ),
+            (
+                "editor",
+                Some("editor::buffer"),
+                Level::Trace,
+                EnabledStatus::Disabled,
+            ),
+            (
+                "editor.selections",
+                None,
+                Level::Debug,
+                EnabledStatus::Enabled,
+            ),
+            // more specific directives override it for everything nested in them
+            ("editor.buffer", None, Level::Debug, EnabledStatus::Disabled),
+            (
+                "editor.buffer.sync",
+                None,
+                Level::Debug,
+                EnabledStatus::Disabled,
+            ),
+            (
+                "editor.buffer.sync",
+                None,
+                Level::Warn,
+                EnabledStatus::Enabled,
+            ),
+            (
+                "editor",
+                Some("editor::display_map::fold_map"),
+                Level::Trace,
+                EnabledStatus::Enabled,
+            ),
+            (
+                "other",
+                Some("other::editor"),
+                Level::Info,
+                EnabledStatus::NotConfigured,
+            ),
+        ] {
+            assert_eq!(
+                map.is_enabled(&scope_from_scope_str(scope), module_path, level),
+                expected,
+                "{scope} {module_path:?} {level}"
+            );
+        }
+    }
+
     #[test]
     fn test_is_enabled_module() {
         let mut map = scope_map_from_keys(&[("a", "trace")]);
@@ -580,6 +944,32 @@ mod tests {
             ),
             EnabledStatus::NotConfigured
         );
+        // nested modules use the directive of their closest configured parent
+        assert_eq!(
+            map.is_enabled(
+                &scope_from_scope_str("__unused__"),
+                Some("a::b::d::e"),
+                Level::Trace
+            ),
+            EnabledStatus::Disabled
+        );
+        assert_eq!(
+            map.is_enabled(
+                &scope_from_scope_str("__unused__"),
+                Some("a::b::c::e"),
+                Level::Trace
+            ),
+            EnabledStatus::Enabled
+        );
+        // but not modules that merely share a name prefix
+        assert_eq!(
+            map.is_enabled(
+                &scope_from_scope_str("__unused__"),
+                Some("a::b::cd"),
+                Level::Info
+            ),
+            EnabledStatus::NotConfigured
+        );
         // when scope is just crate name, more specific module path overrides it
         assert_eq!(
             map.is_enabled(&scope_from_scope_str("a"), Some("a::b::d"), Level::Trace),
@@ -820,13 +1210,473 @@ mod tests {
             map.is_enabled(
                 &scope_new(&[""]),
                 Some("crate::module::default::sub"),
-                Level::Error
+                Level::Warn
             ),
+            EnabledStatus::Disabled,
+            "Module crate::module::default::sub should inherit the crate::module::default filter"
+        );
+    }
+
+    #[test]
+    fn env_off_overrides_global_level() {
+        let env_filter = env_config::parse("debug,editor=off").unwrap();
+        assert_eq!(env_filter.level_global, Some(LevelFilter::Debug));
+        let map = scope_map_from_keys_and_env(&[], &env_filter);
+
+        use log::Level;
+        for level in [Level::Error, Level::Warn, Level::Info, Level::Debug] {
+            assert_eq!(
+                map.is_enabled(&scope_new(&["editor"]), None, level),
+                EnabledStatus::Disabled,
+                "editor=off should disable {level} even though the global level is debug"
+            );
+            assert_eq!(
+                map.is_enabled(&scope_new(&["editor", "sub"]), None, level),
+                EnabledStatus::Disabled,
+                "editor=off should disable {level} for sub scopes of editor"
+            );
+        }
+        assert_eq!(
+            map.is_enabled(&scope_new(&["project"]), None, Level::Debug),
             EnabledStatus::NotConfigured,
-            "Module crate::module::default::sub should not be affected by crate::module::default filter"
+            "scopes not mentioned fall back to the global level"
         );
     }
 
+    #[test]
+    fn env_global_level_is_default_for_unconfigured_scopes() {
+        let env_filter = env_config::parse("info,editor=debug").unwrap();
+        assert_eq!(env_filter.level_global, Some(LevelFilter::Info));
+        assert_eq!(env_filter.directive_names, ["editor"]);
+        let map = scope_map_from_keys_and_env(&[], &env_filter);
+
+        // mirrors `is_scope_enabled`, with the global level as the default
+        let is_enabled = |scope: &[&'static str], level: log::Level| match map.is_enabled(
+            &scope_new(scope),
+            None,
+            level,
+        ) {
+            EnabledStatus::Enabled => true,
+            EnabledStatus::Disabled => false,
+            EnabledStatus::NotConfigured => level <= env_filter.level_global.unwrap(),
+        };
+
+        use log::Level;
+        assert!(is_enabled(&["project"], Level::Info));
+        assert!(!is_enabled(&["project"], Level::Debug));
+        assert!(is_enabled(&["editor"], Level::Debug));
+        assert!(is_enabled(&["editor", "sub"], Level::Debug));
+        assert!(!is_enabled(&["editor"], Level::Trace));
+    }
+
+    #[test]
+    fn env_wildcard_takes_precedence_over_global_level() {
+        use log::Level;
+        let is_enabled = |env: &str, scope: &[&'static str], level: Level| {
+            let env_filter = env_config::parse(env).unwrap();
+            let map = scope_map_from_keys_and_env(&[], &env_filter);
+            filter_core::is_scope_enabled(
+                &map,
+                env_filter.level_default().unwrap(),
+                &scope_new(scope),
+                None,
+                level,
+            )
+        };
+
+        // only `*` applies
+        assert!(is_enabled(
+            "warn,*=info,editor=debug",
+            &["project"],
+            Level::Info
+        ));
+        assert!(!is_enabled(
+            "warn,*=info,editor=debug",
+            &["project"],
+            Level::Debug
+        ));
+        // the specific directive overrides `*`
+        assert!(is_enabled(
+            "warn,*=info,editor=debug",
+            &["editor"],
+            Level::Debug
+        ));
+        assert!(is_enabled(
+            "warn,*=info,editor=debug",
+            &["editor", "sub"],
+            Level::Debug
+        ));
+        assert!(is_enabled(
+            "warn,*=error,editor=debug",
+            &["editor"],
+            Level::Info
+        ));
+        assert!(!is_enabled(
+            "warn,*=error,editor=debug",
+            &["project"],
+            Level::Warn
+        ));
+        // neither applies, so the bare level is used
+        assert!(is_enabled("warn,editor=debug", &["project"], Level::Warn));
+        assert!(!is_enabled("warn,editor=debug", &["project"], Level::Info));
+    }
+
+    #[test]
+    fn to_env_filter_lists_configured_scopes() {
+        let map = scope_map_from_keys(&[
+            ("project", "info"),
+            ("editor.sub.deep", "trace"),
+            ("editor", "warn"),
+            ("crate::module", "off"),
+        ]);
+        assert_eq!(
+            map.to_env_filter(Some(LevelFilter::Error)).to_env_string(),
+            "error,crate::module=off,editor=warn,editor.sub.deep=trace,project=info"
+        );
+        assert_eq!(
+            scope_map_from_keys(&[]).to_env_filter(None).to_env_string(),
+            ""
+        );
+    }
+
+    #[test]
+    fn suppressed_counts_per_scope() {
+        use log::Level;
+
+        let _lock = REFRESH_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        set_count_suppressed(true);
+        for _ in 0..3 {
+            assert!(!is_scope_enabled(
+                &["suppressed_counts", "sub", "", ""],
+                None,
+                Level::Trace
+            ));
+        }
+        assert!(!is_scope_enabled(
+            &["", "", "", ""],
+            Some("suppressed_crate::module"),
+            Level::Trace
+        ));
+        assert!(is_scope_enabled(
+            &["suppressed_counts", "sub", "", ""],
+            None,
+            Level::Error
+        ));
+        set_count_suppressed(false);
+        assert!(!is_scope_enabled(
+            &["suppressed_counts", "sub", "", ""],
+            None,
+            Level::Trace
+        ));
+
+        let counts = suppressed_counts();
+        assert_eq!(counts.get("suppressed_counts.sub"), Some(&3));
+        assert_eq!(counts.get("suppressed_crate"), Some(&1));
+    }
+
+    #[test]
+    fn mute_and_unmute_scope() {
+        use log::Level;
+
+        let _lock = REFRESH_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        let scope = ["mute_test", "noisy", "sub", ""];
+        let sibling = ["mute_test", "quiet", "", ""];
+        assert!(is_scope_enabled(&scope, None, Level::Error));
+
+        mute_scope(&["mute_test", "noisy"]);
+        assert!(!is_scope_enabled(&scope, None, Level::Error));
+        assert!(!is_scope_enabled(
+            &["mute_test", "noisy", "", ""],
+            None,
+            Level::Error
+        ));
+        assert!(is_scope_enabled(&sibling, None, Level::Error));
+
+        mute_scope(&["mute_crate"]);
+        assert!(!is_scope_enabled(
+            &["", "", "", ""],
+            Some("mute_crate::module"),
+            Level::Error
+        ));
+
+        unmute_scope(&["mute_test", "noisy"]);
+        unmute_scope(&["mute_crate"]);
+        assert!(is_scope_enabled(&scope, None, Level::Error));
+        assert!(is_scope_enabled(
+            &["", "", "", ""],
+            Some("mute_crate::module"),
+            Level::Error
+        ));
+    }
+
+    #[test]
+    fn refresh_logs_applied_filter() {
+        let _lock = REFRESH_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        crate::sink::captured_output();
+        refresh_from_settings(&HashMap::from_iter([(
+            "refresh_summary_test".to_string(),
+            "warn".to_string(),
+        )]));
+        crate::flush();
+
+        let output = crate::sink::captured_output();
+        let line = output
+            .lines()
+            .find(|line| line.contains("refresh_summary_test=warn"))
+            .expect("applied filter was logged");
+        assert!(
+            line.contains(" INFO  [zlog.filter] Log filter applied: "),
+            "{line}"
+        );
+
+        // Settings stores refresh on every change, most of which don't touch
+        // the filter.
+        refresh_from_settings(&HashMap::from_iter([(
+            "refresh_summary_test".to_string(),
+            "warn".to_string(),
+        )]));
+        crate::flush();
+        let output = crate::sink::captured_output();
+        let applied = output
+            .lines()
+            .filter(|line| line.contains("refresh_summary_test=warn"))
+            .count();
+        assert_eq!(applied, 1, "{output}");
+    }
+
+    #[test]
+    fn refresh_applies_default_level() {
+        use log::Level;
+        let _lock = REFRESH_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        let settings =
+            HashMap::from_iter([("default_level_test.quiet".to_string(), "warn".to_string())]);
+
+        refresh_from_settings_with_default_level(Some("trace"), &settings);
+        assert!(is_possibly_enabled_level(Level::Trace));
+        assert!(is_scope_enabled(
+            &["default_level_test", "", "", ""],
+            None,
+            Level::Trace
+        ));
+        assert!(!is_scope_enabled(
+            &["default_level_test", "quiet", "", ""],
+            None,
+            Level::Info
+        ));
+
+        refresh_from_settings(&settings);
+        assert!(!is_scope_enabled(
+            &["default_level_test", "", "", ""],
+            None,
+            Level::Trace
+        ));
+        assert!(is_scope_enabled(
+            &["default_level_test", "", "", ""],
+            None,
+            Level::Info
+        ));
+    }
+
+    #[test]
+    fn with_level_restores_after_block() {
+        use log::Level;
+        let _lock = REFRESH_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        refresh_from_settings(&HashMap::default());
+        let scope = ["with_level_test", "", "", ""];
+        let trace_enabled = || is_scope_enabled(&scope, None, Level::Trace);
+
+        assert!(!trace_enabled());
+        with_level(log::LevelFilter::Trace, || {
+            assert!(trace_enabled());
+            with_level(log::LevelFilter::Debug, || {
+                assert!(!trace_enabled());
+                assert!(is_scope_enabled(&scope, None, Level::Debug));
+            });
+            assert!(trace_enabled());
+        });
+        assert!(!trace_enabled());
+        assert!(!is_possibly_enabled_level(Level::Trace));
+
+        let result = std::panic::catch_unwind(|| {
+            with_level(log::LevelFilter::Trace, || panic!("inside with_level"))
+        });
+        assert!(result.is_err());
+        assert!(!trace_enabled());
+
+        // Other threads keep their level, and settings refreshed inside the
+        // block stay applied after it.
+        let quiet = ["with_level_test", "quiet", "", ""];
+        with_level(log::LevelFilter::Trace, || {
+            std::thread::scope(|scope| {
+                scope.spawn(|| assert!(!trace_enabled())).join().unwrap();
+            });
+            refresh_from_settings(&HashMap::from_iter([(
+                "with_level_test.quiet".to_string(),
+                "error".to_string(),
+            )]));
+            assert!(trace_enabled());
+            assert!(!is_scope_enabled(&quiet, None, Level::Info));
+        });
+        assert!(!trace_enabled());
+        assert!(!is_scope_enabled(&quiet, None, Level::Info));
+        assert!(is_scope_enabled(&scope, None, Level::Info));
+    }
+
+    #[test]
+    fn scope_alloc_matches_static_scope() {
+        use log::Level;
+        let _lock = REFRESH_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        refresh_from_settings(&HashMap::from_iter([
+            ("alloc_test".to_string(), "debug".to_string()),
+            ("alloc_test.quiet".to_string(), "error".to_string()),
+            ("alloc_test_module".to_string(), "trace".to_string()),
+        ]));
+
+        let scopes: [&[&str]; 5] = [
+            &["alloc_test"],
+            &["alloc_test", "quiet"],
+            &["alloc_test", "quiet", "nested"],
+            &["alloc_test_other"],
+            &[],
+        ];
+        let module_paths = [None, Some("alloc_test_module::sub")];
+        let levels = [Level::Error, Level::Info, Level::Debug, Level::Trace];
+        for scope in scopes {
+            let mut scope_ref = [""; SCOPE_DEPTH_MAX];
+            scope_ref[..scope.len()].copy_from_slice(scope);
+            let scope_alloc = private::scope_alloc_new(scope);
+            for module_path in module_paths {
+                for level in levels {
+                    assert_eq!(
+                        is_scope_alloc_enabled(&scope_alloc, module_path, level),
+                        is_scope_enabled(&scope_ref, module_path, level),
+                        "{scope:?} {module_path:?} {level}"
+                    );
+                }
+            }
+        }
+        assert!(is_scope_alloc_enabled(
+            &private::scope_alloc_new(&["alloc_test"]),
+            None,
+            Level::Debug
+        ));
+        assert!(!is_scope_alloc_enabled(
+            &private::scope_alloc_new(&["alloc_test", "quiet"]),
+            None,
+            Level::Warn
+        ));
+
+        refresh_from_settings(&HashMap::default());
+    }
+
+    #[test]
+    fn snapshot_reflects_filter() {
+        let _lock = REFRESH_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        refresh_from_settings_with_default_level(
+            Some("warn"),
+            &HashMap::from_iter([
+                ("snapshot_test".to_string(), "debug".to_string()),
+                ("snapshot_test.noisy".to_string(), "off".to_string()),
+                ("snapshot_crate::module".to_string(), "trace".to_string()),
+            ]),
+        );
+        mute_scope(&["snapshot_test", "muted"]);
+
+        let snapshot = snapshot();
+        unmute_scope(&["snapshot_test", "muted"]);
+        refresh_from_settings(&HashMap::default());
+
+        assert_eq!(snapshot.default_level, log::LevelFilter::Warn);
+        assert!(!snapshot.disabled);
+        assert!(snapshot.scopes.contains(&ScopeLevel {
+            scope: "snapshot_test".to_string(),
+            level: log::LevelFilter::Debug,
+        }));
+        assert!(snapshot.scopes.contains(&ScopeLevel {
+            scope: "snapshot_crate::module".to_string(),
+            level: log::LevelFilter::Trace,
+        }));
+        assert!(
+            snapshot
+                .excluded
+                .contains(&"snapshot_test.noisy".to_string())
+        );
+        assert!(
+            !snapshot
+                .scopes
+                .iter()
+                .any(|scope_level| scope_level.scope == "snapshot_test.noisy")
+        );
+        assert!(snapshot.muted.contains(&"snapshot_test.muted".to_string()));
+
+        let json = serde_json::to_value(&snapshot).unwrap();
+        assert_eq!(json["default_level"], "WARN");
+        assert!(
+            json["scopes"]
+                .as_array()
+                .unwrap()
+                .contains(&serde_json::json!({ "scope": "snapshot_test", "level": "DEBUG" }))
+        );
+    }
+
+    #[test]
+    fn namespaces_filter_independently() {
+        use log::Level;
+        let plugin_host = namespace("namespace_test_plugin_host");
+        let language_server = namespace("namespace_test_language_server");
+        assert!(std::ptr::eq(
+            plugin_host,
+            namespace("namespace_test_plugin_host")
+        ));
+
+        plugin_host.refresh_from_settings(&HashMap::from_iter([(
+            "extension".to_string(),
+            "debug".to_string(),
+        )]));
+        language_server.refresh_from_settings(&HashMap::from_iter([(
+            "extension".to_string(),
+            "off".to_string(),
+        )]));
+        language_server.set_default_level(LevelFilter::Warn);
+
+        let scope = ["extension", "", "", ""];
+        assert!(plugin_host.is_scope_enabled(&scope, None, Level::Debug));
+        assert!(!language_server.is_scope_enabled(&scope, None, Level::Error));
+        assert!(plugin_host.is_scope_enabled(&["other", "", "", ""], None, Level::Info));
+        assert!(!language_server.is_scope_enabled(&["other", "", "", ""], None, Level::Info));
+        assert!(language_server.is_scope_enabled(&["other", "", "", ""], None, Level::Warn));
+
+        crate::sink::captured_output();
+        for namespace in [plugin_host, language_server] {
+            namespace.submit(crate::sink::Record {
+                scope: ["extension", "", "", ""],
+                level: Level::Debug,
+                message: &format_args!("submitted through {}", namespace.name()),
+                module_path: None,
+                line: None,
+                seq: 0,
+            });
+        }
+        crate::flush();
+        let output = crate::sink::captured_output();
+        assert!(output.contains("submitted through namespace_test_plugin_host"));
+        assert!(!output.contains("submitted through namespace_test_language_server"));
+    }
+
     #[test]
     fn default_filter_crate() {
         let default_filters = &[("crate", LevelFilter::Off)];
@@ -839,4 +1689,91 @@ mod tests {
             "crate::submodule should be disabled by disabling `crate` filter"
         );
     }
+
+    /// The straightforward linear lookup that `ScopeMap::search` replaced.
+    fn search_linear(map: &ScopeMap, scope: &Scope) -> Option<LevelFilter> {
+        let mut enabled = None;
+        let mut cur_range = &map.entries[0..map.root_count];
+        let mut depth = 0;
+        'search: while !cur_range.is_empty() && depth < SCOPE_DEPTH_MAX && scope[depth] != "" {
+            for entry in cur_range {
+                if entry.scope == scope[depth] {
+                    enabled = entry.enabled.or(enabled);
+                    cur_range = &map.entries[entry.descendants.clone()];
+                    depth += 1;
+                    continue 'search;
+                }
+            }
+            break 'search;
+        }
+        enabled
+    }
+
+    #[test]
+    fn search_matches_linear_lookup() {
+        let map = scope_map_from_keys(&[
+            ("zeta", "error"),
+            ("alpha.beta", "debug"),
+            ("alpha", "warn"),
+            ("alpha.beta.gamma.delta", "trace"),
+            ("mid.b", "off"),
+            ("mid.a", "info"),
+            ("mid.c.x", "trace"),
+            ("crate::module", "debug"),
+            ("crate::a_module", "off"),
+        ]);
+        let scopes = [
+            "zeta",
+            "zeta.sub",
+            "alpha",
+            "alpha.beta",
+            "alpha.beta.gamma",
+            "alpha.beta.gamma.delta",
+            "alpha.other",
+            "mid",
+            "mid.a",
+            "mid.b",
+            "mid.c",
+            "mid.c.x",
+            "mid.c.y",
+            "missing",
+            "missing.alpha",
+        ];
+        let module_paths = [None, Some("crate::module"), Some("crate::a_module")];
+        use log::Level;
+        let levels = [
+            Level::Error,
+            Level::Warn,
+            Level::Info,
+            Level::Debug,
+            Level::Trace,
+        ];
+
+        for scope_str in scopes {
+            let scope = scope_from_scope_str(scope_str);
+            let expected = search_linear(&map, &scope);
+            assert_eq!(map.search(&scope), expected, "scope {scope_str}");
+
+            for module_path in module_paths {
+                for level in levels {
+                    let expected_status = match expected {
+                        Some(filter) if level <= filter => EnabledStatus::Enabled,
+                        Some(_) => EnabledStatus::Disabled,
+                        None => match module_path {
+                            Some("crate::module") if level <= LevelFilter::Debug => {
+                                EnabledStatus::Enabled
+                            }
+                            Some(_) => EnabledStatus::Disabled,
+                            None => EnabledStatus::NotConfigured,
+                        },
+                    };
+                    assert_eq!(
+                        map.is_enabled(&scope, module_path, level),
+                        expected_status,
+                        "scope {scope_str}, module {module_path:?}, level {level}"
+                    );
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,477 @@
+//! The scope and level filter logic, free of global state and of `std`, so that
+//! it can be used without the rest of zlog. `filter` applies it to the
+//! process-wide configuration.
+
+use alloc::{
+    borrow::ToOwned,
+    collections::VecDeque,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::ops::Range;
+
+use crate::{SCOPE_DEPTH_MAX, SCOPE_STRING_SEP_STR, ScopeAlloc, ScopeRef, env_config, private};
+
+pub use crate::env_config::{EnvFilter, ParseError, ParseErrorKind, Sampling, parse, parse_level};
+
+/// Warns about invalid configuration. zlog may not be the `log` logger, so its
+/// own sinks are used when they're built.
+macro_rules! warn_invalid {
+    ($($arg:tt)+) => {
+        #[cfg(feature = "std")]
+        crate::warn!($($arg)+);
+        #[cfg(not(feature = "std"))]
+        log::warn!($($arg)+);
+    };
+}
+
+pub(crate) fn level_filter_from_str(level_str: &str) -> Option<log::LevelFilter> {
+    if level_str.is_empty() {
+        return Some(log::LevelFilter::Trace);
+    }
+    match env_config::parse_level(level_str) {
+        Ok(level) => Some(level),
+        Err(_)
+            if matches!(
+                level_str.to_ascii_lowercase().as_str(),
+                "disable" | "no" | "disabled"
+            ) =>
+        {
+            warn_invalid!(
+                "Invalid log level \"{level_str}\", to disable logging set to \"off\". Defaulting to \"off\"."
+            );
+            Some(log::LevelFilter::Off)
+        }
+        Err(_) => {
+            warn_invalid!("Invalid log level \"{level_str}\", ignoring");
+            None
+        }
+    }
+}
+
+pub(crate) fn scope_alloc_from_scope_str(scope_str: &str) -> Option<ScopeAlloc> {
+    let mut scope_buf = [""; SCOPE_DEPTH_MAX];
+    let mut index = 0;
+    let mut scope_iter = scope_str.split(SCOPE_STRING_SEP_STR);
+    while index < SCOPE_DEPTH_MAX {
+        let Some(scope) = scope_iter.next() else {
+            break;
+        };
+        if scope.is_empty() {
+            continue;
+        }
+        scope_buf[index] = scope;
+        index += 1;
+    }
+    if index == 0 {
+        return None;
+    }
+    if scope_iter.next().is_some() {
+        warn_invalid!(
+            "Invalid scope key, too many nested scopes: '{scope_str}'. Max depth is {SCOPE_DEPTH_MAX}",
+        );
+        return None;
+    }
+    let scope = scope_buf.map(|s| s.to_string());
+    Some(scope)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ScopeMap {
+    pub(crate) entries: Vec<ScopeMapEntry>,
+    pub(crate) modules: Vec<(String, log::LevelFilter)>,
+    pub(crate) root_count: usize,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ScopeMapEntry {
+    pub(crate) scope: String,
+    pub(crate) enabled: Option<log::LevelFilter>,
+    pub(crate) descendants: Range<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnabledStatus {
+    Enabled,
+    Disabled,
+    NotConfigured,
+}
+
+impl ScopeMap {
+    /// Builds the map from settings, as pairs of scope and level names, the
+    /// filter parsed from `ZED_LOG` and the built-in defaults. Later sources
+    /// take precedence.
+    ///
+    /// Names that differ only in empty segments, such as `a.b` and `a..b`,
+    /// configure the same scope. Among those, the last `ZED_LOG` directive
+    /// wins, and since settings have no order, the settings key that sorts
+    /// last wins, so the result doesn't depend on map iteration order.
+    pub fn new_from_settings_and_env<K, V>(
+        settings: impl IntoIterator<Item = (K, V)>,
+        env_config: Option<&env_config::EnvFilter>,
+        default_filters: &[(&str, log::LevelFilter)],
+    ) -> Self
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let mut settings = settings
+            .into_iter()
+            .filter_map(|(scope_str, level_str)| {
+                let level_filter = level_filter_from_str(level_str.as_ref())?;
+                Some((scope_str, level_filter))
+            })
+            .collect::<Vec<_>>();
+        settings.sort_by(|(a_name, _), (b_name, _)| a_name.as_ref().cmp(b_name.as_ref()));
+        let mut items = Vec::<(ScopeAlloc, log::LevelFilter)>::with_capacity(
+            settings.len()
+                + env_config.map_or(0, |c| c.directive_names.len())
+                + default_filters.len(),
+        );
+        let mut modules = Vec::with_capacity(4);
+
+        let env_filters = env_config.iter().flat_map(|env_filter| {
+            env_filter
+                .directive_names
+                .iter()
+                .zip(env_filter.directive_levels.iter())
+                .map(|(scope_str, level_filter)| (scope_str.as_str(), *level_filter))
+        });
+
+        let new_filters = settings
+            .iter()
+            .map(|(scope_str, level_filter)| (scope_str.as_ref(), *level_filter));
+
+        let all_filters = default_filters
+            .iter()
+            .cloned()
+            .chain(env_filters)
+            .chain(new_filters);
+
+        for (scope_str, level_filter) in all_filters {
+            if scope_str.contains("::") {
+                if let Some(idx) = modules.iter().position(|(module, _)| module == scope_str) {
+                    modules[idx].1 = level_filter;
+                } else {
+                    modules.push((scope_str.to_string(), level_filter));
+                }
+                continue;
+            }
+            let Some(scope) = scope_alloc_from_scope_str(scope_str) else {
+                continue;
+            };
+            if let Some(idx) = items
+                .iter()
+                .position(|(scope_existing, _)| scope_existing == &scope)
+            {
+                items[idx].1 = level_filter;
+            } else {
+                items.push((scope, level_filter));
+            }
+        }
+
+        items.sort_by(|a, b| a.0.cmp(&b.0));
+        modules.sort_by(|(a_name, _), (b_name, _)| a_name.cmp(b_name));
+
+        let mut this = Self {
+            entries: Vec::with_capacity(items.len() * SCOPE_DEPTH_MAX),
+            modules,
+            root_count: 0,
+        };
+
+        let items_count = items.len();
+
+        struct ProcessQueueEntry {
+            parent_index: usize,
+            depth: usize,
+            items_range: Range<usize>,
+        }
+        let mut process_queue = VecDeque::new();
+        process_queue.push_back(ProcessQueueEntry {
+            parent_index: usize::MAX,
+            depth: 0,
+            items_range: 0..items_count,
+        });
+
+        let empty_range = 0..0;
+
+        while let Some(process_entry) = process_queue.pop_front() {
+            let ProcessQueueEntry {
+                items_range,
+                depth,
+                parent_index,
+            } = process_entry;
+            let mut cursor = items_range.start;
+            let res_entries_start = this.entries.len();
+            while cursor < items_range.end {
+                let sub_items_start = cursor;
+                cursor += 1;
+                let scope_name = &items[sub_items_start].0[depth];
+                while cursor < items_range.end && &items[cursor].0[depth] == scope_name {
+                    cursor += 1;
+                }
+                let sub_items_end = cursor;
+                if scope_name.is_empty() {
+                    assert_eq!(sub_items_start + 1, sub_items_end);
+                    assert_ne!(depth, 0);
+                    assert_ne!(parent_index, usize::MAX);
+                    assert!(this.entries[parent_index].enabled.is_none());
+                    this.entries[parent_index].enabled = Some(items[sub_items_start].1);
+                    continue;
+                }
+                let is_valid_scope = !scope_name.is_empty();
+                let is_last = depth + 1 == SCOPE_DEPTH_MAX || !is_valid_scope;
+                let mut enabled = None;
+                if is_last {
+                    assert_eq!(
+                        sub_items_start + 1,
+                        sub_items_end,
+                        "Expected one item: got: {:?}",
+                        &items[items_range]
+                    );
+                    enabled = Some(items[sub_items_start].1);
+                } else {
+                    let entry_index = this.entries.len();
+                    process_queue.push_back(ProcessQueueEntry {
+                        items_range: sub_items_start..sub_items_end,
+                        parent_index: entry_index,
+                        depth: depth + 1,
+                    });
+                }
+                this.entries.push(ScopeMapEntry {
+                    scope: scope_name.to_owned(),
+                    enabled,
+                    descendants: empty_range.clone(),
+                });
+            }
+            let res_entries_end = this.entries.len();
+            if parent_index != usize::MAX {
+                this.entries[parent_index].descendants = res_entries_start..res_entries_end;
+            } else {
+                this.root_count = res_entries_end;
+            }
+        }
+
+        this
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty() && self.modules.is_empty()
+    }
+
+    pub fn is_enabled<S>(
+        &self,
+        scope: &[S; SCOPE_DEPTH_MAX],
+        module_path: Option<&str>,
+        level: log::Level,
+    ) -> EnabledStatus
+    where
+        S: AsRef<str>,
+    {
+        let mut enabled = self.search(scope);
+
+        if let Some(module_path) = module_path {
+            let scope_is_empty = scope[0].as_ref().is_empty();
+
+            if enabled.is_none() && scope_is_empty {
+                let crate_name = private::extract_crate_name_from_module_path(module_path);
+                let mut crate_name_scope = [""; SCOPE_DEPTH_MAX];
+                crate_name_scope[0] = crate_name;
+                enabled = self.search(&crate_name_scope);
+            }
+
+            if !self.modules.is_empty() {
+                let crate_name = private::extract_crate_name_from_module_path(module_path);
+                let is_scope_just_crate_name =
+                    scope[0].as_ref() == crate_name && scope[1].as_ref() == "";
+                if (enabled.is_none() || is_scope_just_crate_name)
+                    && let Some(module_enabled) = self.search_module(module_path)
+                {
+                    enabled = Some(module_enabled);
+                }
+            }
+        }
+
+        if let Some(enabled_filter) = enabled {
+            if level <= enabled_filter {
+                return EnabledStatus::Enabled;
+            }
+            return EnabledStatus::Disabled;
+        }
+        EnabledStatus::NotConfigured
+    }
+
+    /// Lists every configured scope and module as `ZED_LOG` directives.
+    #[cfg(feature = "std")]
+    pub(crate) fn to_env_filter(
+        &self,
+        level_global: Option<log::LevelFilter>,
+    ) -> env_config::EnvFilter {
+        fn collect(
+            map: &ScopeMap,
+            range: Range<usize>,
+            prefix: &str,
+            directives: &mut Vec<(String, log::LevelFilter)>,
+        ) {
+            for entry in &map.entries[range] {
+                let name = if prefix.is_empty() {
+                    entry.scope.clone()
+                } else {
+                    alloc::format!("{prefix}{SCOPE_STRING_SEP_STR}{}", entry.scope)
+                };
+                if let Some(level) = entry.enabled {
+                    directives.push((name.clone(), level));
+                }
+                collect(map, entry.descendants.clone(), &name, directives);
+            }
+        }
+
+        let mut directives = Vec::new();
+        collect(self, 0..self.root_count, "", &mut directives);
+        directives.extend(self.modules.iter().cloned());
+        let (directive_names, directive_levels) = directives.into_iter().unzip();
+        env_config::EnvFilter {
+            level_global,
+            level_wildcard: None,
+            directive_names,
+            directive_levels,
+            sampling: Vec::new(),
+        }
+    }
+
+    /// Finds the most specific configured level for `scope`. Siblings at each
+    /// depth are sorted by name when the map is built, so each segment is a
+    /// binary search.
+    pub(crate) fn search<S>(&self, scope: &[S; SCOPE_DEPTH_MAX]) -> Option<log::LevelFilter>
+    where
+        S: AsRef<str>,
+    {
+        let mut enabled = None;
+        let mut cur_range = &self.entries[0..self.root_count];
+        for segment in scope.iter().map(AsRef::as_ref) {
+            if cur_range.is_empty() || segment.is_empty() {
+                break;
+            }
+            let Ok(index) = cur_range.binary_search_by(|entry| entry.scope.as_str().cmp(segment))
+            else {
+                break;
+            };
+            let entry = &cur_range[index];
+            enabled = entry.enabled.or(enabled);
+            cur_range = &self.entries[entry.descendants.clone()];
+        }
+        enabled
+    }
+
+    /// Finds the level of the most specific module directive for
+    /// `module_path`, which is either the module itself or one it's nested in.
+    fn search_module(&self, mut module_path: &str) -> Option<log::LevelFilter> {
+        loop {
+            if let Ok(index) = self
+                .modules
+                .binary_search_by(|(module, _)| module.as_str().cmp(module_path))
+            {
+                return Some(self.modules[index].1);
+            }
+            (module_path, _) = module_path.rsplit_once("::")?;
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) const fn empty() -> ScopeMap {
+        ScopeMap {
+            entries: Vec::new(),
+            modules: Vec::new(),
+            root_count: 0,
+        }
+    }
+}
+
+/// Whether a record at `level` from `scope` passes `scope_map`, using
+/// `level_default` for scopes and modules it doesn't configure. This is the
+/// decision `filter::is_scope_enabled` makes with the global configuration.
+pub fn is_scope_enabled(
+    scope_map: &ScopeMap,
+    level_default: log::LevelFilter,
+    scope: &ScopeRef<'_>,
+    module_path: Option<&str>,
+    level: log::Level,
+) -> bool {
+    let is_enabled_by_default = level <= level_default;
+    if scope_map.is_empty() {
+        return is_enabled_by_default;
+    }
+    match scope_map.is_enabled(scope, module_path, level) {
+        EnabledStatus::NotConfigured => is_enabled_by_default,
+        EnabledStatus::Enabled => true,
+        EnabledStatus::Disabled => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::{Level, LevelFilter};
+
+    use super::*;
+    use crate::private::scope_ref_new;
+
+    #[test]
+    fn is_scope_enabled_falls_back_to_default_level() {
+        let map = ScopeMap::new_from_settings_and_env(
+            [("a", "debug"), ("a.b", "off"), ("c::d", "trace")],
+            None,
+            &[],
+        );
+        for (scope, module_path, level, expected) in [
+            (&["a"][..], None, Level::Debug, true),
+            (&["a"], None, Level::Trace, false),
+            (&["a", "b"], None, Level::Error, false),
+            (&["x"], None, Level::Warn, true),
+            (&["x"], None, Level::Info, false),
+            (&["c"], Some("c::d"), Level::Trace, true),
+            (&["c"], Some("c::e"), Level::Trace, false),
+        ] {
+            assert_eq!(
+                is_scope_enabled(
+                    &map,
+                    LevelFilter::Warn,
+                    &scope_ref_new(scope),
+                    module_path,
+                    level
+                ),
+                expected,
+                "{scope:?} {module_path:?} {level}"
+            );
+        }
+
+        let empty = ScopeMap::new_from_settings_and_env([("", "")], None, &[]);
+        assert!(empty.is_empty());
+        assert!(is_scope_enabled(
+            &empty,
+            LevelFilter::Info,
+            &scope_ref_new(&["a"]),
+            None,
+            Level::Info
+        ));
+    }
+
+    #[test]
+    fn equally_specific_names_resolve_deterministically() {
+        let settings = [("tie.break", "off"), (".tie..break", "trace")];
+        let scope = scope_ref_new(&["tie", "break"]);
+        for settings in [settings, [settings[1], settings[0]]] {
+            let map = ScopeMap::new_from_settings_and_env(settings, None, &[]);
+            assert_eq!(
+                map.is_enabled(&scope, None, Level::Error),
+                EnabledStatus::Disabled
+            );
+        }
+
+        let env_filter = env_config::parse("tie.break=off,tie..break=trace").unwrap();
+        let map = ScopeMap::new_from_settings_and_env(None::<(&str, &str)>, Some(&env_filter), &[]);
+        assert_eq!(
+            map.is_enabled(&scope, None, Level::Trace),
+            EnabledStatus::Enabled
+        );
+    }
+}
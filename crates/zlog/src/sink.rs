@@ -1,14 +1,18 @@
 use std::{
+    borrow::Cow,
     fs,
     io::{self, Write},
     path::PathBuf,
     sync::{
-        Mutex, OnceLock,
-        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard, OnceLock, RwLock, Weak,
+        atomic::{AtomicBool, AtomicI32, AtomicU8, AtomicU64, AtomicUsize, Ordering},
     },
+    time::{Duration, Instant},
 };
 
-use crate::{SCOPE_STRING_SEP_CHAR, ScopeRef};
+use serde::Serialize;
+
+use crate::{SCOPE_STRING_SEP_CHAR, ScopeAlloc, ScopeRef};
 
 // ANSI color escape codes for log levels
 const ANSI_RESET: &str = "\x1b[0m";
@@ -21,6 +25,19 @@ const ANSI_MAGENTA: &str = "\x1b[35m";
 
 /// Is Some(file) if file output is enabled.
 static ENABLED_SINKS_FILE: Mutex<Option<std::fs::File>> = Mutex::new(None);
+/// Whether file output is enabled. Mirrors `ENABLED_SINKS_FILE` so that records
+/// can be rendered without taking the file lock.
+static ENABLED_SINKS_FILE_FLAG: AtomicBool = AtomicBool::new(false);
+/// Is Some(writer) if output to a custom writer is enabled.
+static ENABLED_SINKS_WRITER: Mutex<Option<Box<dyn Write + Send>>> = Mutex::new(None);
+/// Whether output to a custom writer is enabled. Mirrors `ENABLED_SINKS_WRITER`.
+static ENABLED_SINKS_WRITER_FLAG: AtomicBool = AtomicBool::new(false);
+/// Receives the records that the file or writer output failed to write.
+static FALLBACK_SINK: Mutex<Option<Box<dyn Write + Send>>> = Mutex::new(None);
+/// When a write to the file or writer output last failed, to throttle warnings.
+static FALLBACK_WARNED_AT: Mutex<Option<Instant>> = Mutex::new(None);
+/// Minimum time between warnings about failed writes.
+const FALLBACK_WARNING_INTERVAL: Duration = Duration::from_secs(60);
 static SINK_FILE_PATH: OnceLock<&'static PathBuf> = OnceLock::new();
 static SINK_FILE_PATH_ROTATE: OnceLock<&'static PathBuf> = OnceLock::new();
 
@@ -29,17 +46,214 @@ static SINK_FILE_PATH_ROTATE: OnceLock<&'static PathBuf> = OnceLock::new();
 static ENABLED_SINKS_STDOUT: AtomicBool = AtomicBool::new(false);
 /// Whether stderr output is enabled.
 static ENABLED_SINKS_STDERR: AtomicBool = AtomicBool::new(false);
+/// Whether console output is split between stdout and stderr by level.
+static ENABLED_SINKS_SPLIT: AtomicBool = AtomicBool::new(false);
+/// Whether rotated log files are gzip-compressed.
+static SINK_FILE_ROTATE_COMPRESS: AtomicBool = AtomicBool::new(false);
 /// Atomic counter for the size of the log file in bytes.
 static SINK_FILE_SIZE_BYTES: AtomicU64 = AtomicU64::new(0);
 /// Maximum size of the log file before it will be rotated, in bytes.
 const SINK_FILE_SIZE_BYTES_MAX: u64 = 1024 * 1024; // 1 MB
+/// Number of records each thread buffers before writing them to the enabled
+/// sinks. Buffered records are also written out by `flush`.
+static BATCH_SIZE: AtomicUsize = AtomicUsize::new(1);
+
+const TIMESTAMP_FORMAT_DEFAULT: &str = TimestampFormat::Rfc3339.strftime();
+/// strftime-style format used to render record timestamps.
+static TIMESTAMP_FORMAT: RwLock<Cow<'static, str>> =
+    RwLock::new(Cow::Borrowed(TIMESTAMP_FORMAT_DEFAULT));
+/// Interval in milliseconds at which the sinks are flushed in the background,
+/// or 0 if periodic flushing is disabled.
+static FLUSH_INTERVAL_MS: AtomicU64 = AtomicU64::new(0);
+static FLUSH_THREAD: OnceLock<std::thread::Thread> = OnceLock::new();
+
+/// The timezone record timestamps are rendered in: `TIMEZONE_LOCAL`,
+/// `TIMEZONE_UTC`, or else a fixed offset from UTC in seconds.
+static TIMESTAMP_TIMEZONE: AtomicI32 = AtomicI32::new(TIMEZONE_LOCAL);
+const TIMEZONE_LOCAL: i32 = i32::MIN;
+const TIMEZONE_UTC: i32 = i32::MAX;
+/// The sequence number of the next submitted record.
+static NEXT_SEQ: AtomicU64 = AtomicU64::new(1);
+static RENDER_SEQ: AtomicBool = AtomicBool::new(false);
+static INSTANCE_TAG: RwLock<Option<&'static str>> = RwLock::new(None);
+/// The message of the banner set by `log_banner`, which starts every new log file.
+static BANNER: RwLock<Option<String>> = RwLock::new(None);
+
+/// The `ColorMode` of console output, by discriminant.
+static COLOR_MODE: AtomicU8 = AtomicU8::new(ColorMode::Auto as u8);
+/// Whether `ColorMode::Auto` colors stdout and stderr, detected once.
+static AUTO_COLORS: OnceLock<(bool, bool)> = OnceLock::new();
+
+/// The `NewlineMode` of each `SinkKind`, by discriminant.
+static NEWLINE_MODES: [AtomicU8; 3] = [const { AtomicU8::new(NewlineMode::Lf as u8) }; 3];
+
+/// Whether text output shows levels as a single letter, see `set_compact_level`.
+static COMPACT_LEVEL: AtomicBool = AtomicBool::new(false);
+
+/// Whether records are rendered as logfmt rather than text, see `OutputFormat`.
+static OUTPUT_LOGFMT: AtomicBool = AtomicBool::new(false);
+
+/// How records are rendered for the console, the log file and custom writers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    /// `<timestamp> <LEVEL> [<scope>] <message> key=value`, with ANSI colors on
+    /// the console.
+    #[default]
+    Text,
+    /// `ts=<timestamp> level=<level> scope=<scope> msg=<message> key=value`,
+    /// with values quoted as needed, for tools that parse logfmt.
+    Logfmt,
+}
+
+/// Whether console output is colored with ANSI escape codes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorMode {
+    /// Colors output to terminals, unless `NO_COLOR` is set to a non-empty
+    /// value or `TERM` is `dumb`.
+    #[default]
+    Auto,
+    /// Always colors output. This takes precedence over `NO_COLOR`, which only
+    /// changes the default, as the spec leaves explicit configuration to
+    /// override it.
+    Always,
+    Never,
+}
+
+/// How each record written to a sink is terminated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NewlineMode {
+    #[default]
+    Lf,
+    /// `\r\n`, for Windows log viewers.
+    CrLf,
+    /// No terminator, for transports that frame records themselves. Each
+    /// record is written to the sink separately.
+    None,
+}
+
+/// The sinks whose line terminator is set with `set_newline_mode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SinkKind {
+    /// Both stdout and stderr.
+    Console,
+    File,
+    /// The writer set with `init_output_writer`.
+    Writer,
+}
+
+/// The timezone record timestamps are rendered in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Timezone {
+    #[default]
+    Local,
+    Utc,
+    /// A fixed offset from UTC, regardless of the local timezone.
+    Fixed(chrono::FixedOffset),
+}
+
+/// Built-in timestamp formats, which can be passed to `set_timestamp_format`
+/// or `SinkConfig::timestamp_format` in place of a strftime-style format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// RFC 3339 with milliseconds and the offset of the configured timezone,
+    /// e.g. `2024-06-01T12:34:56.789+02:00`. This is the default.
+    Rfc3339,
+}
+
+impl TimestampFormat {
+    pub const fn strftime(self) -> &'static str {
+        match self {
+            TimestampFormat::Rfc3339 => "%Y-%m-%dT%H:%M:%S%.3f%:z",
+        }
+    }
+}
+
+impl From<TimestampFormat> for Cow<'static, str> {
+    fn from(format: TimestampFormat) -> Self {
+        Cow::Borrowed(format.strftime())
+    }
+}
+
+/// The syslog severity of each level, indexed from `Error` to `Trace`.
+pub const SEVERITY_MAP_DEFAULT: [u8; 5] = [3, 4, 6, 7, 7];
+/// Severity numbers reported to external sinks, indexed from `Error` to `Trace`.
+static SEVERITY_MAP: [AtomicU8; 5] = [
+    AtomicU8::new(SEVERITY_MAP_DEFAULT[0]),
+    AtomicU8::new(SEVERITY_MAP_DEFAULT[1]),
+    AtomicU8::new(SEVERITY_MAP_DEFAULT[2]),
+    AtomicU8::new(SEVERITY_MAP_DEFAULT[3]),
+    AtomicU8::new(SEVERITY_MAP_DEFAULT[4]),
+];
+
+/// How `init_output_file` treats the contents of an existing log file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OpenMode {
+    /// Keep the existing contents and append new records after them. Files that
+    /// have already reached the rotation size are rotated first.
+    #[default]
+    Append,
+    /// Discard the existing contents and start with an empty file.
+    Truncate,
+}
 
 pub struct Record<'a> {
     pub scope: ScopeRef<'a>,
     pub level: log::Level,
     pub message: &'a std::fmt::Arguments<'a>,
-    pub module_path: Option<&'a str>,
+    pub module_path: Option<&'static str>,
+    pub line: Option<u32>,
+    /// The position of the record among all records, assigned by [`submit`]
+    /// from a counter that starts at 1. Records that haven't been submitted
+    /// have 0.
+    pub seq: u64,
+}
+
+impl Record<'_> {
+    pub fn to_owned(&self) -> OwnedRecord {
+        OwnedRecord::from(self)
+    }
+}
+
+/// A [`Record`] that owns its data, so that it can be sent to other threads
+/// and rendered later.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OwnedRecord {
+    pub scope: ScopeAlloc,
+    pub level: log::Level,
+    pub message: String,
+    pub module_path: Option<&'static str>,
     pub line: Option<u32>,
+    pub seq: u64,
+}
+
+impl From<&Record<'_>> for OwnedRecord {
+    fn from(record: &Record<'_>) -> Self {
+        Self {
+            scope: record.scope.map(str::to_owned),
+            level: record.level,
+            message: record.message.to_string(),
+            module_path: record.module_path,
+            line: record.line,
+            seq: record.seq,
+        }
+    }
+}
+
+impl OwnedRecord {
+    /// Calls `f` with a borrowed [`Record`] of this record's data, e.g. to pass
+    /// it on to [`submit`].
+    pub fn with_record<R>(&self, f: impl FnOnce(Record<'_>) -> R) -> R {
+        f(Record {
+            scope: self.scope.each_ref().map(String::as_str),
+            level: self.level,
+            message: &format_args!("{}", self.message),
+            module_path: self.module_path,
+            line: self.line,
+            seq: self.seq,
+        })
+    }
 }
 
 pub fn init_output_stdout() {
@@ -52,9 +266,367 @@ pub fn init_output_stderr() {
     ENABLED_SINKS_STDERR.store(true, Ordering::Release);
 }
 
+/// Writes warnings and errors to stderr, and all other records to stdout, so
+/// that callers can redirect them separately. Takes precedence over
+/// `init_output_stdout` and `init_output_stderr`.
+pub fn init_output_split() {
+    ENABLED_SINKS_SPLIT.store(true, Ordering::Release);
+}
+
+/// The console streams that records are written to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ConsoleOutput {
+    Stdout,
+    Stderr,
+    /// Warnings and errors go to stderr, everything else to stdout.
+    Split,
+}
+
+fn console_output() -> Option<ConsoleOutput> {
+    if ENABLED_SINKS_SPLIT.load(Ordering::Acquire) {
+        Some(ConsoleOutput::Split)
+    } else if ENABLED_SINKS_STDOUT.load(Ordering::Acquire) {
+        Some(ConsoleOutput::Stdout)
+    } else if ENABLED_SINKS_STDERR.load(Ordering::Acquire) {
+        Some(ConsoleOutput::Stderr)
+    } else {
+        None
+    }
+}
+
+/// Writes records to `writer`, formatted the same way as for the log file.
+/// Replaces any writer set by a previous call.
+pub fn init_output_writer(writer: Box<dyn Write + Send>) {
+    let mut enabled_sinks_writer = ENABLED_SINKS_WRITER.lock().unwrap_or_else(|handle| {
+        ENABLED_SINKS_WRITER.clear_poison();
+        handle.into_inner()
+    });
+    *enabled_sinks_writer = Some(writer);
+    ENABLED_SINKS_WRITER_FLAG.store(true, Ordering::Release);
+}
+
+/// Process-level metadata written by `init_output_json_with_meta`, so that
+/// consumers of a log stream can correlate it with the process that wrote it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ProcessMeta {
+    pub pid: u32,
+    pub app_version: String,
+    pub host: String,
+    pub start_time: chrono::DateTime<chrono::Utc>,
+}
+
+/// Like `init_output_writer`, but first writes `meta` to `writer` as a single
+/// line of JSON. The records that follow are written one per line, as usual.
+pub fn init_output_json_with_meta(
+    mut writer: Box<dyn Write + Send>,
+    meta: &ProcessMeta,
+) -> io::Result<()> {
+    let mut header = serde_json::to_vec(meta)?;
+    match newline_mode(SinkKind::Writer) {
+        NewlineMode::Lf => header.push(b'\n'),
+        NewlineMode::CrLf => header.extend_from_slice(b"\r\n"),
+        NewlineMode::None => {}
+    }
+    writer.write_all(&header)?;
+    init_output_writer(writer);
+    Ok(())
+}
+
+/// Writes the records that the file or writer output fails to write, e.g.
+/// because the disk is full, to `writer` instead, such as `io::stderr()`.
+/// Replaces any fallback set by a previous call.
+pub fn init_output_fallback(writer: Box<dyn Write + Send>) {
+    *FALLBACK_SINK.lock().unwrap_or_else(|handle| {
+        FALLBACK_SINK.clear_poison();
+        handle.into_inner()
+    }) = Some(writer);
+}
+
+/// Writes `bytes` to `sink`, falling back to the fallback sink if that fails.
+/// A warning about the failure is written at most once per
+/// `FALLBACK_WARNING_INTERVAL`.
+fn write_with_fallback(sink: &mut (impl Write + ?Sized), bytes: &[u8]) {
+    let Err(err) = sink.write_all(bytes) else {
+        return;
+    };
+    let warn = {
+        let mut warned_at = FALLBACK_WARNED_AT.lock().unwrap_or_else(|handle| {
+            FALLBACK_WARNED_AT.clear_poison();
+            handle.into_inner()
+        });
+        let now = Instant::now();
+        let warn = warned_at
+            .is_none_or(|warned_at| now.duration_since(warned_at) >= FALLBACK_WARNING_INTERVAL);
+        if warn {
+            *warned_at = Some(now);
+        }
+        warn
+    };
+    let mut fallback = FALLBACK_SINK.lock().unwrap_or_else(|handle| {
+        FALLBACK_SINK.clear_poison();
+        handle.into_inner()
+    });
+    let Some(fallback) = fallback.as_mut() else {
+        if warn {
+            eprintln!("Failed to write to log output, records are being lost: {err}");
+        }
+        return;
+    };
+    if warn {
+        _ = writeln!(
+            fallback,
+            "Failed to write to log output, writing records here instead: {err}"
+        );
+    }
+    _ = fallback.write_all(bytes);
+}
+
+/// The severity number external sinks such as journald report for `level`.
+pub fn severity_of(level: log::Level) -> u8 {
+    SEVERITY_MAP[level as usize - 1].load(Ordering::Acquire)
+}
+
+/// Overrides the severity numbers reported to external sinks, indexed from
+/// `Error` to `Trace`. Defaults to `SEVERITY_MAP_DEFAULT`.
+pub fn set_severity_map(map: [u8; 5]) {
+    for (severity, value) in SEVERITY_MAP.iter().zip(map) {
+        severity.store(value, Ordering::Release);
+    }
+}
+
+/// Sets how many records each thread buffers before writing them out, trading
+/// latency of log output for less contention on the shared sinks.
+///
+/// Defaults to 1, i.e. records are written as soon as they are submitted.
+pub fn set_batch_size(batch_size: usize) {
+    BATCH_SIZE.store(batch_size.max(1), Ordering::Release);
+}
+
+/// Sets whether rotated log files are gzip-compressed to `<path_rotate>.gz`.
+///
+/// Compression happens on a background thread so that logging isn't stalled.
+/// The uncompressed rotated file is only removed once the compressed file has
+/// been fully written.
+pub fn set_rotate_compression(compress: bool) {
+    SINK_FILE_ROTATE_COMPRESS.store(compress, Ordering::Release);
+}
+
+/// Sets the strftime-style format used to render record timestamps, e.g.
+/// `%H:%M:%S%.3f`. Returns an error, leaving the format unchanged, if `format`
+/// contains an invalid specifier.
+pub fn set_timestamp_format(format: impl Into<Cow<'static, str>>) -> anyhow::Result<()> {
+    let format = format.into();
+    validate_timestamp_format(&format)?;
+    *TIMESTAMP_FORMAT.write().unwrap_or_else(|handle| {
+        TIMESTAMP_FORMAT.clear_poison();
+        handle.into_inner()
+    }) = format;
+    Ok(())
+}
+
+fn validate_timestamp_format(format: &str) -> anyhow::Result<()> {
+    if chrono::format::StrftimeItems::new(format)
+        .any(|item| matches!(item, chrono::format::Item::Error))
+    {
+        anyhow::bail!("Invalid timestamp format {format:?}");
+    }
+    Ok(())
+}
+
+/// Tags every line of text output with `tag`, e.g. a process or instance id,
+/// and reports it to the OTLP collector as the `service.instance.id`. No tag
+/// is added by default.
+pub fn set_instance_tag(tag: &'static str) {
+    *INSTANCE_TAG.write().unwrap_or_else(|handle| {
+        INSTANCE_TAG.clear_poison();
+        handle.into_inner()
+    }) = Some(tag);
+}
+
+pub fn instance_tag() -> Option<&'static str> {
+    *INSTANCE_TAG.read().unwrap_or_else(|handle| {
+        INSTANCE_TAG.clear_poison();
+        handle.into_inner()
+    })
+}
+
+/// Logs the build identity of the app, along with the OS and architecture, as
+/// an info record that isn't subject to filtering. `version` may include
+/// anything that identifies the build, such as a commit sha.
+///
+/// The banner is also written as the first line of every log file opened
+/// afterwards, including each file started by rotation.
+pub fn log_banner(app_name: &str, version: &str) {
+    let message = format!(
+        "{app_name} {version} ({} {})",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    submit(banner_record(&format_args!("{message}")));
+    *BANNER.write().unwrap_or_else(|handle| {
+        BANNER.clear_poison();
+        handle.into_inner()
+    }) = Some(message);
+}
+
+fn banner_record<'a>(message: &'a std::fmt::Arguments<'a>) -> Record<'a> {
+    Record {
+        scope: ["zlog", "banner", "", ""],
+        level: log::Level::Info,
+        message,
+        module_path: Some(module_path!()),
+        line: None,
+        seq: 0,
+    }
+}
+
+/// Writes the banner set by `log_banner`, if any, to a newly opened log file,
+/// returning the number of bytes written.
+fn write_banner(file: &mut impl Write) -> u64 {
+    let banner = BANNER.read().unwrap_or_else(|handle| {
+        BANNER.clear_poison();
+        handle.into_inner()
+    });
+    let Some(message) = banner.as_deref() else {
+        return 0;
+    };
+    let line = format_record(&banner_record(&format_args!("{message}"))) + "\n";
+    match file.write_all(line.as_bytes()) {
+        Ok(()) => line.len() as u64,
+        Err(_) => 0,
+    }
+}
+
+/// Renders each record's sequence number in text output, as `#<seq>` before
+/// the timestamp, so that lines that were dropped or reordered can be spotted.
+/// Sequence numbers are not rendered by default.
+pub fn set_render_seq(render: bool) {
+    RENDER_SEQ.store(render, Ordering::Release);
+}
+
+fn rendered_seq(record: &Record) -> Option<u64> {
+    RENDER_SEQ.load(Ordering::Acquire).then_some(record.seq)
+}
+
+/// Sets the timezone record timestamps are rendered in. Defaults to local time.
+pub fn set_timestamp_timezone(timezone: Timezone) {
+    let timezone = match timezone {
+        Timezone::Local => TIMEZONE_LOCAL,
+        Timezone::Utc => TIMEZONE_UTC,
+        Timezone::Fixed(offset) => offset.local_minus_utc(),
+    };
+    TIMESTAMP_TIMEZONE.store(timezone, Ordering::Release);
+}
+
+/// Sets whether console output is colored. Defaults to `ColorMode::Auto`.
+pub fn set_color_mode(mode: ColorMode) {
+    COLOR_MODE.store(mode as u8, Ordering::Release);
+}
+
+pub fn color_mode() -> ColorMode {
+    match COLOR_MODE.load(Ordering::Acquire) {
+        mode if mode == ColorMode::Always as u8 => ColorMode::Always,
+        mode if mode == ColorMode::Never as u8 => ColorMode::Never,
+        _ => ColorMode::Auto,
+    }
+}
+
+/// Whether records written to stdout, or to stderr if `stderr` is set, are
+/// colored.
+fn console_colors(stderr: bool) -> bool {
+    match color_mode() {
+        ColorMode::Always => true,
+        ColorMode::Never => false,
+        ColorMode::Auto => {
+            let (stdout_colors, stderr_colors) = *AUTO_COLORS.get_or_init(|| {
+                use std::io::IsTerminal;
+                let no_color = std::env::var_os("NO_COLOR");
+                let term = std::env::var_os("TERM");
+                let colors =
+                    |is_terminal| auto_colors(is_terminal, no_color.as_deref(), term.as_deref());
+                (
+                    colors(io::stdout().is_terminal()),
+                    colors(io::stderr().is_terminal()),
+                )
+            });
+            if stderr { stderr_colors } else { stdout_colors }
+        }
+    }
+}
+
+/// Whether `ColorMode::Auto` colors a stream, given the values of `NO_COLOR`
+/// and `TERM`.
+fn auto_colors(
+    is_terminal: bool,
+    no_color: Option<&std::ffi::OsStr>,
+    term: Option<&std::ffi::OsStr>,
+) -> bool {
+    is_terminal
+        && no_color.is_none_or(|no_color| no_color.is_empty())
+        && term != Some("dumb".as_ref())
+}
+
+/// Shows levels in text output as a single letter, `E`, `W`, `I`, `D` or `T`,
+/// rather than the full name. Defaults to the full name.
+pub fn set_compact_level(compact: bool) {
+    COMPACT_LEVEL.store(compact, Ordering::Release);
+}
+
+pub fn compact_level() -> bool {
+    COMPACT_LEVEL.load(Ordering::Acquire)
+}
+
+/// Sets how records written to `sink` are terminated. Defaults to
+/// `NewlineMode::Lf`.
+pub fn set_newline_mode(sink: SinkKind, mode: NewlineMode) {
+    NEWLINE_MODES[sink as usize].store(mode as u8, Ordering::Release);
+}
+
+pub fn newline_mode(sink: SinkKind) -> NewlineMode {
+    match NEWLINE_MODES[sink as usize].load(Ordering::Acquire) {
+        mode if mode == NewlineMode::CrLf as u8 => NewlineMode::CrLf,
+        mode if mode == NewlineMode::None as u8 => NewlineMode::None,
+        _ => NewlineMode::Lf,
+    }
+}
+
+/// Sets how records are rendered. Defaults to text.
+pub fn set_output_format(format: OutputFormat) {
+    OUTPUT_LOGFMT.store(format == OutputFormat::Logfmt, Ordering::Release);
+}
+
+pub fn output_format() -> OutputFormat {
+    if OUTPUT_LOGFMT.load(Ordering::Acquire) {
+        OutputFormat::Logfmt
+    } else {
+        OutputFormat::Text
+    }
+}
+
+pub fn timestamp_timezone() -> Timezone {
+    match TIMESTAMP_TIMEZONE.load(Ordering::Acquire) {
+        TIMEZONE_LOCAL => Timezone::Local,
+        TIMEZONE_UTC => Timezone::Utc,
+        offset => chrono::FixedOffset::east_opt(offset).map_or(Timezone::Utc, Timezone::Fixed),
+    }
+}
+
+fn format_timestamp<'a>(
+    time: &chrono::DateTime<chrono::Utc>,
+    timezone: Timezone,
+    format: &'a str,
+) -> chrono::format::DelayedFormat<chrono::format::StrftimeItems<'a>> {
+    match timezone {
+        Timezone::Local => time.with_timezone(&chrono::Local).format(format),
+        Timezone::Utc => time.format(format),
+        Timezone::Fixed(offset) => time.with_timezone(&offset).format(format),
+    }
+}
+
 pub fn init_output_file(
     path: &'static PathBuf,
     path_rotate: Option<&'static PathBuf>,
+    mode: OpenMode,
 ) -> io::Result<()> {
     let mut enabled_sinks_file = ENABLED_SINKS_FILE
         .try_lock()
@@ -69,9 +641,14 @@ pub fn init_output_file(
             .expect("Init file output should only be called once");
     }
 
-    let file = open_or_create_log_file(path, path_rotate, SINK_FILE_SIZE_BYTES_MAX)?;
-    SINK_FILE_SIZE_BYTES.store(file.metadata().map_or(0, |m| m.len()), Ordering::Release);
+    let mut file = open_or_create_log_file(path, path_rotate, mode, SINK_FILE_SIZE_BYTES_MAX)?;
+    let mut file_size_bytes = file.metadata().map_or(0, |m| m.len());
+    if file_size_bytes == 0 {
+        file_size_bytes = write_banner(&mut file);
+    }
+    SINK_FILE_SIZE_BYTES.store(file_size_bytes, Ordering::Release);
     *enabled_sinks_file = Some(file);
+    ENABLED_SINKS_FILE_FLAG.store(true, Ordering::Release);
 
     Ok(())
 }
@@ -79,13 +656,24 @@ pub fn init_output_file(
 fn open_or_create_log_file(
     path: &PathBuf,
     path_rotate: Option<&PathBuf>,
+    mode: OpenMode,
     sink_file_size_bytes_max: u64,
 ) -> Result<fs::File, io::Error> {
+    if mode == OpenMode::Truncate {
+        return std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(path);
+    }
     let size_bytes = std::fs::metadata(path).map(|metadata| metadata.len());
     match size_bytes {
-        Ok(size_bytes) if size_bytes >= sink_file_size_bytes_max => {
-            rotate_log_file(Some(path), path_rotate).map(|it| it.unwrap())
-        }
+        Ok(size_bytes) if size_bytes >= sink_file_size_bytes_max => rotate_log_file(
+            Some(path),
+            path_rotate,
+            SINK_FILE_ROTATE_COMPRESS.load(Ordering::Acquire),
+        )
+        .map(|it| it.unwrap()),
         _ => std::fs::OpenOptions::new()
             .create(true)
             .append(true)
@@ -102,6 +690,10 @@ const LEVEL_OUTPUT_STRINGS: [&str; 6] = [
     "TRACE", //
 ];
 
+/// Level names used instead of `LEVEL_OUTPUT_STRINGS` if `set_compact_level`
+/// is set.
+const LEVEL_COMPACT_STRINGS: [&str; 6] = [" ", "E", "W", "I", "D", "T"];
+
 // Colors for different log levels
 static LEVEL_ANSI_COLORS: [&str; 6] = [
     "",           // nop
@@ -112,121 +704,719 @@ static LEVEL_ANSI_COLORS: [&str; 6] = [
     ANSI_MAGENTA, // Trace: Magenta
 ];
 
-// PERF: batching
-pub fn submit(mut record: Record) {
-    if record.module_path.is_none_or(|p| !p.ends_with(".rs")) {
-        // Only render line numbers for actual rust files emitted by `log_err` and friends
-        record.line.take();
+thread_local! {
+    static THREAD_BATCH: ThreadBatch = ThreadBatch::register();
+}
+
+/// Batches of every thread that has submitted a record, so that `flush` can
+/// drain records that are still buffered on other threads.
+static BATCHES: Mutex<Vec<Weak<Mutex<RecordBatch>>>> = Mutex::new(Vec::new());
+
+/// Owns the calling thread's batch, and writes out whatever is left in it when
+/// the thread exits.
+struct ThreadBatch(Arc<Mutex<RecordBatch>>);
+
+impl ThreadBatch {
+    fn register() -> Self {
+        let batch = Arc::new(Mutex::new(RecordBatch::default()));
+        let mut batches = BATCHES.lock().unwrap_or_else(|handle| {
+            BATCHES.clear_poison();
+            handle.into_inner()
+        });
+        batches.retain(|batch| batch.strong_count() > 0);
+        batches.push(Arc::downgrade(&batch));
+        Self(batch)
     }
-    if ENABLED_SINKS_STDOUT.load(Ordering::Acquire) {
-        let mut stdout = std::io::stdout().lock();
-        _ = writeln!(
-            &mut stdout,
-            "{} {ANSI_BOLD}{}{}{ANSI_RESET} {} {}",
-            chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%:z"),
-            LEVEL_ANSI_COLORS[record.level as usize],
-            LEVEL_OUTPUT_STRINGS[record.level as usize],
-            SourceFmt {
-                scope: record.scope,
-                module_path: record.module_path,
-                line: record.line,
-                ansi: true,
+
+    fn lock(&self) -> MutexGuard<'_, RecordBatch> {
+        self.0.lock().unwrap_or_else(|handle| {
+            self.0.clear_poison();
+            handle.into_inner()
+        })
+    }
+}
+
+impl Drop for ThreadBatch {
+    fn drop(&mut self) {
+        self.lock().drain(write_to_sinks);
+    }
+}
+
+/// Records that have been rendered but not yet written to the enabled sinks.
+#[derive(Default)]
+struct RecordBatch {
+    /// Records rendered for stdout, including ANSI color codes.
+    console: Vec<u8>,
+    /// Records rendered for stderr, including ANSI color codes.
+    console_stderr: Vec<u8>,
+    /// Records rendered for the log file and custom writer.
+    file: Vec<u8>,
+    /// The offsets at which each record in `console`, `console_stderr` and
+    /// `file` ends, so that sinks can change their line terminators.
+    console_ends: Vec<usize>,
+    console_stderr_ends: Vec<usize>,
+    file_ends: Vec<usize>,
+    len: usize,
+}
+
+impl RecordBatch {
+    /// Renders `record` along with the calling thread's context, so that the
+    /// context is captured when the record is submitted.
+    fn push(&mut self, record: &Record, console: Option<ConsoleOutput>, file: bool) {
+        let timestamp_format = TIMESTAMP_FORMAT.read().unwrap_or_else(|handle| {
+            TIMESTAMP_FORMAT.clear_poison();
+            handle.into_inner()
+        });
+        let timestamp =
+            format_timestamp(&chrono::Utc::now(), timestamp_timezone(), &timestamp_format);
+        let instance_tag = instance_tag();
+        let seq = rendered_seq(record);
+        let format = output_format();
+        let compact_level = compact_level();
+        crate::context::with_current(|context| {
+            let stdout = (&mut self.console, &mut self.console_ends, false);
+            let stderr = (
+                &mut self.console_stderr,
+                &mut self.console_stderr_ends,
+                true,
+            );
+            let console = match console {
+                Some(ConsoleOutput::Stdout) => Some(stdout),
+                Some(ConsoleOutput::Stderr) => Some(stderr),
+                Some(ConsoleOutput::Split) if record.level <= log::Level::Warn => Some(stderr),
+                Some(ConsoleOutput::Split) => Some(stdout),
+                None => None,
+            };
+            if let Some((console, ends, stderr)) = console {
+                write_record(
+                    console,
+                    record,
+                    format,
+                    compact_level,
+                    instance_tag,
+                    seq,
+                    &timestamp,
+                    context,
+                    console_colors(stderr),
+                );
+                ends.push(console.len());
+            }
+            if file {
+                write_record(
+                    &mut self.file,
+                    record,
+                    format,
+                    compact_level,
+                    instance_tag,
+                    seq,
+                    &timestamp,
+                    context,
+                    false,
+                );
+                self.file_ends.push(self.file.len());
+            }
+        });
+        self.len += 1;
+    }
+
+    fn drain(&mut self, write: impl FnOnce(&RenderedRecords, &RenderedRecords, &RenderedRecords)) {
+        if self.len == 0 {
+            return;
+        }
+        write(
+            &RenderedRecords {
+                bytes: &self.console,
+                ends: &self.console_ends,
             },
-            record.message
-        );
-    } else if ENABLED_SINKS_STDERR.load(Ordering::Acquire) {
-        let mut stdout = std::io::stderr().lock();
-        _ = writeln!(
-            &mut stdout,
-            "{} {ANSI_BOLD}{}{}{ANSI_RESET} {} {}",
-            chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%:z"),
-            LEVEL_ANSI_COLORS[record.level as usize],
-            LEVEL_OUTPUT_STRINGS[record.level as usize],
-            SourceFmt {
-                scope: record.scope,
-                module_path: record.module_path,
-                line: record.line,
-                ansi: true,
+            &RenderedRecords {
+                bytes: &self.console_stderr,
+                ends: &self.console_stderr_ends,
+            },
+            &RenderedRecords {
+                bytes: &self.file,
+                ends: &self.file_ends,
             },
-            record.message
         );
+        self.console.clear();
+        self.console_stderr.clear();
+        self.file.clear();
+        self.console_ends.clear();
+        self.console_stderr_ends.clear();
+        self.file_ends.clear();
+        self.len = 0;
     }
-    let mut file_guard = ENABLED_SINKS_FILE.lock().unwrap_or_else(|handle| {
-        ENABLED_SINKS_FILE.clear_poison();
-        handle.into_inner()
-    });
-    if let Some(file) = file_guard.as_mut() {
-        struct SizedWriter<'a> {
-            file: &'a mut std::fs::File,
-            written: u64,
-        }
-        impl io::Write for SizedWriter<'_> {
-            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-                self.file.write(buf)?;
-                self.written += buf.len() as u64;
-                Ok(buf.len())
-            }
+}
 
-            fn flush(&mut self) -> io::Result<()> {
-                self.file.flush()
-            }
-        }
-        let file_size_bytes = {
-            let mut writer = SizedWriter { file, written: 0 };
-            _ = writeln!(
-                &mut writer,
-                "{} {} {} {}",
-                chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%:z"),
-                LEVEL_OUTPUT_STRINGS[record.level as usize],
-                SourceFmt {
-                    scope: record.scope,
-                    module_path: record.module_path,
-                    line: record.line,
-                    ansi: false,
-                },
-                record.message
-            );
-            SINK_FILE_SIZE_BYTES.fetch_add(writer.written, Ordering::AcqRel) + writer.written
+/// Records rendered into a batch, each ending with `\n`.
+struct RenderedRecords<'a> {
+    bytes: &'a [u8],
+    /// The offset at which each record ends.
+    ends: &'a [usize],
+}
+
+impl std::ops::Deref for RenderedRecords<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.bytes
+    }
+}
+
+impl RenderedRecords<'_> {
+    /// Passes the records to `write` with the line terminator of `mode`,
+    /// returning the number of bytes written. With `NewlineMode::None`, each
+    /// record is passed separately so that the sink can frame it.
+    fn write(&self, mode: NewlineMode, mut write: impl FnMut(&[u8])) -> usize {
+        let records = || {
+            let starts = std::iter::once(0).chain(self.ends.iter().copied());
+            starts.zip(self.ends).map(|(start, &end)| {
+                let record = &self.bytes[start..end];
+                record.strip_suffix(b"\n").unwrap_or(record)
+            })
         };
-        if file_size_bytes > SINK_FILE_SIZE_BYTES_MAX {
-            *file_guard = None;
-            let file = rotate_log_file(SINK_FILE_PATH.get(), SINK_FILE_PATH_ROTATE.get());
-            match file {
-                Ok(Some(file)) => *file_guard = Some(file),
-                Ok(None) => {}
-                Err(e) => {
-                    eprintln!("Failed to open log file: {e}")
+        match mode {
+            NewlineMode::Lf => {
+                write(self.bytes);
+                self.bytes.len()
+            }
+            NewlineMode::CrLf => {
+                let mut bytes = Vec::with_capacity(self.bytes.len() + self.ends.len());
+                for record in records() {
+                    bytes.extend_from_slice(record);
+                    bytes.extend_from_slice(b"\r\n");
                 }
+                write(&bytes);
+                bytes.len()
             }
-            SINK_FILE_SIZE_BYTES.store(0, Ordering::Release);
+            NewlineMode::None => records()
+                .map(|record| {
+                    write(record);
+                    record.len()
+                })
+                .sum(),
         }
     }
 }
 
-pub fn flush() {
-    if ENABLED_SINKS_STDOUT.load(Ordering::Acquire) {
-        _ = std::io::stdout().lock().flush();
+/// Writes `record` as a single line in `format`, with ANSI colors for the
+/// console if `ansi` is set and the format is text. Text output shows the
+/// level as a single letter if `compact_level` is set.
+fn write_record(
+    out: &mut Vec<u8>,
+    record: &Record,
+    format: OutputFormat,
+    compact_level: bool,
+    instance_tag: Option<&str>,
+    seq: Option<u64>,
+    timestamp: &impl std::fmt::Display,
+    context: &[(&'static str, String)],
+    ansi: bool,
+) {
+    if format == OutputFormat::Logfmt {
+        write_record_logfmt(out, record, instance_tag, seq, timestamp, context);
+        return;
     }
-    let mut file = ENABLED_SINKS_FILE.lock().unwrap_or_else(|handle| {
-        ENABLED_SINKS_FILE.clear_poison();
+    if let Some(instance_tag) = instance_tag {
+        _ = write!(out, "{instance_tag} ");
+    }
+    if let Some(seq) = seq {
+        _ = write!(out, "#{seq} ");
+    }
+    let source = SourceFmt {
+        scope: record.scope,
+        module_path: record.module_path,
+        line: record.line,
+        ansi,
+    };
+    let level = if compact_level {
+        LEVEL_COMPACT_STRINGS[record.level as usize]
+    } else {
+        LEVEL_OUTPUT_STRINGS[record.level as usize]
+    };
+    if ansi {
+        _ = writeln!(
+            out,
+            "{} {ANSI_BOLD}{}{}{ANSI_RESET} {} {}{}",
+            timestamp,
+            LEVEL_ANSI_COLORS[record.level as usize],
+            level,
+            source,
+            record.message,
+            ContextFmt(context),
+        );
+    } else {
+        _ = writeln!(
+            out,
+            "{} {} {} {}{}",
+            timestamp,
+            level,
+            source,
+            record.message,
+            ContextFmt(context),
+        );
+    }
+}
+
+fn write_record_logfmt(
+    out: &mut Vec<u8>,
+    record: &Record,
+    instance_tag: Option<&str>,
+    seq: Option<u64>,
+    timestamp: &impl std::fmt::Display,
+    context: &[(&'static str, String)],
+) {
+    _ = write!(
+        out,
+        "ts={} level={} scope={}",
+        LogfmtValue(&timestamp.to_string()),
+        record.level.as_str().to_ascii_lowercase(),
+        LogfmtValue(&scope_name(record)),
+    );
+    if let Some(line) = record.line {
+        _ = write!(out, " line={line}");
+    }
+    if let Some(instance_tag) = instance_tag {
+        _ = write!(out, " instance={}", LogfmtValue(instance_tag));
+    }
+    if let Some(seq) = seq {
+        _ = write!(out, " seq={seq}");
+    }
+    _ = write!(out, " msg={}", LogfmtValue(&record.message.to_string()));
+    for (key, value) in context {
+        _ = write!(out, " {key}={}", LogfmtValue(value));
+    }
+    out.push(b'\n');
+}
+
+fn assign_seq(record: &mut Record) {
+    record.seq = NEXT_SEQ.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Only render line numbers for actual rust files emitted by `log_err` and friends
+fn strip_non_source_line(record: &mut Record) {
+    if record.module_path.is_none_or(|p| !p.ends_with(".rs")) {
+        record.line.take();
+    }
+}
+
+/// Renders `record` exactly as it's written to the log file, using the
+/// configured timestamp format and the calling thread's context, but without
+/// the trailing newline.
+pub fn format_record(record: &Record) -> String {
+    let mut record = Record { ..*record };
+    strip_non_source_line(&mut record);
+    let timestamp_format = TIMESTAMP_FORMAT.read().unwrap_or_else(|handle| {
+        TIMESTAMP_FORMAT.clear_poison();
         handle.into_inner()
     });
-    if let Some(file) = file.as_mut()
-        && let Err(err) = file.flush()
-    {
-        eprintln!("Failed to flush log file: {}", err);
-    }
+    let timestamp = format_timestamp(&chrono::Utc::now(), timestamp_timezone(), &timestamp_format);
+    let mut line = Vec::new();
+    crate::context::with_current(|context| {
+        write_record(
+            &mut line,
+            &record,
+            output_format(),
+            compact_level(),
+            instance_tag(),
+            rendered_seq(&record),
+            &timestamp,
+            context,
+            false,
+        );
+    });
+    line.pop();
+    String::from_utf8_lossy(&line).into_owned()
 }
 
-struct SourceFmt<'a> {
-    scope: ScopeRef<'a>,
-    module_path: Option<&'a str>,
-    line: Option<u32>,
-    ansi: bool,
+/// Identifies a callback registered with [`subscribe`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+type Subscriber = Arc<dyn Fn(&OwnedRecord) + Send + Sync>;
+
+static SUBSCRIBERS: RwLock<Vec<(SubscriptionId, Subscriber)>> = RwLock::new(Vec::new());
+/// Whether `SUBSCRIBERS` is non-empty, so that records are only copied for
+/// subscribers when there are any.
+static ANY_SUBSCRIBERS: AtomicBool = AtomicBool::new(false);
+static NEXT_SUBSCRIPTION_ID: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// Set while this thread runs subscribers, so that records they log aren't
+    /// passed back to them.
+    static IN_SUBSCRIBER: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
 }
 
-impl std::fmt::Display for SourceFmt<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+/// Calls `callback` with every record that passes the filters, e.g. to show
+/// records live in the UI, until [`unsubscribe`] is called with the returned
+/// id. Callbacks run synchronously on the logging thread, so they should be
+/// cheap, or forward the record to a channel to handle it elsewhere. Records
+/// logged from within a callback are not passed to subscribers.
+pub fn subscribe(callback: Box<dyn Fn(&OwnedRecord) + Send + Sync>) -> SubscriptionId {
+    let id = SubscriptionId(NEXT_SUBSCRIPTION_ID.fetch_add(1, Ordering::Relaxed));
+    let mut subscribers = SUBSCRIBERS.write().unwrap_or_else(|handle| {
+        SUBSCRIBERS.clear_poison();
+        handle.into_inner()
+    });
+    subscribers.push((id, Arc::from(callback)));
+    ANY_SUBSCRIBERS.store(true, Ordering::Release);
+    id
+}
+
+/// Removes a callback registered with [`subscribe`]. It may still be running
+/// on other threads when this returns.
+pub fn unsubscribe(id: SubscriptionId) {
+    let mut subscribers = SUBSCRIBERS.write().unwrap_or_else(|handle| {
+        SUBSCRIBERS.clear_poison();
+        handle.into_inner()
+    });
+    subscribers.retain(|(subscription_id, _)| *subscription_id != id);
+    ANY_SUBSCRIBERS.store(!subscribers.is_empty(), Ordering::Release);
+}
+
+fn notify_subscribers(record: &Record) {
+    if IN_SUBSCRIBER.try_with(|in_subscriber| in_subscriber.replace(true)) != Ok(false) {
+        return;
+    }
+    // Called without the lock held, so that subscribers can subscribe and
+    // unsubscribe.
+    let subscribers = SUBSCRIBERS
+        .read()
+        .unwrap_or_else(|handle| {
+            SUBSCRIBERS.clear_poison();
+            handle.into_inner()
+        })
+        .iter()
+        .map(|(_, subscriber)| subscriber.clone())
+        .collect::<Vec<_>>();
+    let record = record.to_owned();
+    for subscriber in subscribers {
+        subscriber(&record);
+    }
+    IN_SUBSCRIBER.with(|in_subscriber| in_subscriber.set(false));
+}
+
+type DurationSink = Arc<dyn Fn(&str, Duration) + Send + Sync>;
+
+static DURATION_SINK: RwLock<Option<DurationSink>> = RwLock::new(None);
+/// Whether `DURATION_SINK` is set, so that timers only measure when they would
+/// log or there is a sink to report to.
+static ANY_DURATION_SINK: AtomicBool = AtomicBool::new(false);
+static LOG_TIMER_DURATIONS: AtomicBool = AtomicBool::new(true);
+
+/// Calls `sink` with the name and duration of every finished [`crate::Timer`],
+/// e.g. to feed a histogram without parsing log text. Timers report to the
+/// sink whether or not their scope is enabled, and still log unless
+/// [`set_log_timer_durations`] turns that off. Replaces any previous sink.
+pub fn set_duration_sink(sink: Box<dyn Fn(&str, Duration) + Send + Sync>) {
+    *DURATION_SINK.write().unwrap_or_else(|handle| {
+        DURATION_SINK.clear_poison();
+        handle.into_inner()
+    }) = Some(Arc::from(sink));
+    ANY_DURATION_SINK.store(true, Ordering::Release);
+}
+
+/// Removes the sink set with [`set_duration_sink`].
+pub fn clear_duration_sink() {
+    ANY_DURATION_SINK.store(false, Ordering::Release);
+    *DURATION_SINK.write().unwrap_or_else(|handle| {
+        DURATION_SINK.clear_poison();
+        handle.into_inner()
+    }) = None;
+}
+
+/// Sets whether finished timers are logged, so that with a duration sink they
+/// can be reported only as metrics. Timers are logged by default.
+pub fn set_log_timer_durations(log: bool) {
+    LOG_TIMER_DURATIONS.store(log, Ordering::Release);
+}
+
+pub(crate) fn has_duration_sink() -> bool {
+    ANY_DURATION_SINK.load(Ordering::Acquire)
+}
+
+pub(crate) fn log_timer_durations() -> bool {
+    LOG_TIMER_DURATIONS.load(Ordering::Acquire)
+}
+
+pub(crate) fn report_duration(name: &str, duration: Duration) {
+    // Called without the lock held, so that the sink can replace itself.
+    let sink = DURATION_SINK
+        .read()
+        .unwrap_or_else(|handle| {
+            DURATION_SINK.clear_poison();
+            handle.into_inner()
+        })
+        .clone();
+    if let Some(sink) = sink {
+        sink(name, duration);
+    }
+}
+
+pub fn submit(mut record: Record) {
+    assign_seq(&mut record);
+    strip_non_source_line(&mut record);
+    if ANY_SUBSCRIBERS.load(Ordering::Acquire) {
+        notify_subscribers(&record);
+    }
+    #[cfg(target_os = "linux")]
+    journald::submit(&record);
+    #[cfg(feature = "otlp")]
+    otlp::submit(&record);
+    let console = console_output();
+    let file = ENABLED_SINKS_FILE_FLAG.load(Ordering::Acquire)
+        || ENABLED_SINKS_WRITER_FLAG.load(Ordering::Acquire);
+    if console.is_none() && !file {
+        return;
+    }
+    let batch_size = BATCH_SIZE.load(Ordering::Acquire);
+    let batched = THREAD_BATCH.try_with(|batch| {
+        let mut batch = batch.lock();
+        batch.push(&record, console, file);
+        if batch.len >= batch_size {
+            batch.drain(write_to_sinks);
+        }
+    });
+    if batched.is_err() {
+        // The thread local batch has already been destroyed, so the thread is
+        // exiting. Write the record out immediately instead.
+        let mut batch = RecordBatch::default();
+        batch.push(&record, console, file);
+        batch.drain(write_to_sinks);
+    }
+}
+
+fn write_to_sinks(
+    console: &RenderedRecords,
+    console_stderr: &RenderedRecords,
+    file: &RenderedRecords,
+) {
+    let console_newline = newline_mode(SinkKind::Console);
+    if !console.is_empty() {
+        let mut stdout = std::io::stdout().lock();
+        console.write(console_newline, |bytes| _ = stdout.write_all(bytes));
+    }
+    if !console_stderr.is_empty() {
+        let mut stderr = std::io::stderr().lock();
+        console_stderr.write(console_newline, |bytes| _ = stderr.write_all(bytes));
+    }
+    if file.is_empty() {
+        return;
+    }
+    if ENABLED_SINKS_WRITER_FLAG.load(Ordering::Acquire) {
+        let mut writer = ENABLED_SINKS_WRITER.lock().unwrap_or_else(|handle| {
+            ENABLED_SINKS_WRITER.clear_poison();
+            handle.into_inner()
+        });
+        if let Some(writer) = writer.as_mut() {
+            file.write(newline_mode(SinkKind::Writer), |bytes| {
+                write_with_fallback(writer.as_mut(), bytes)
+            });
+        }
+    }
+    let mut file_guard = ENABLED_SINKS_FILE.lock().unwrap_or_else(|handle| {
+        ENABLED_SINKS_FILE.clear_poison();
+        handle.into_inner()
+    });
+    let Some(sink_file) = file_guard.as_mut() else {
+        return;
+    };
+    let written = file.write(newline_mode(SinkKind::File), |bytes| {
+        write_with_fallback(sink_file, bytes)
+    }) as u64;
+    let file_size_bytes = SINK_FILE_SIZE_BYTES.fetch_add(written, Ordering::AcqRel) + written;
+    if file_size_bytes > SINK_FILE_SIZE_BYTES_MAX {
+        *file_guard = None;
+        let file = rotate_log_file(
+            SINK_FILE_PATH.get(),
+            SINK_FILE_PATH_ROTATE.get(),
+            SINK_FILE_ROTATE_COMPRESS.load(Ordering::Acquire),
+        );
+        let mut file_size_bytes = 0;
+        match file {
+            Ok(Some(mut file)) => {
+                file_size_bytes = write_banner(&mut file);
+                *file_guard = Some(file);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                eprintln!("Failed to open log file: {e}")
+            }
+        }
+        SINK_FILE_SIZE_BYTES.store(file_size_bytes, Ordering::Release);
+    }
+}
+
+/// Writes out the records buffered by every thread.
+fn drain_batches() {
+    let mut batches = BATCHES.lock().unwrap_or_else(|handle| {
+        BATCHES.clear_poison();
+        handle.into_inner()
+    });
+    batches.retain(|batch| {
+        let Some(batch) = batch.upgrade() else {
+            return false;
+        };
+        let mut batch = batch.lock().unwrap_or_else(|handle| {
+            batch.clear_poison();
+            handle.into_inner()
+        });
+        batch.drain(write_to_sinks);
+        true
+    });
+}
+
+/// Writes out all buffered records and waits for background sinks to handle
+/// them, so that nothing logged before the call is lost if the process exits.
+/// Sinks that fail to flush are reported on stderr, see `try_flush`.
+pub fn flush() {
+    if let Err(err) = try_flush() {
+        eprintln!("{err}");
+    }
+}
+
+/// Like `flush`, but returns the failures instead of reporting them. Every sink
+/// is flushed even if an earlier one fails, in the order OTLP, stdout, writer,
+/// file, and the error names each sink that failed.
+pub fn try_flush() -> anyhow::Result<()> {
+    let mut failures = Vec::new();
+    drain_batches();
+    #[cfg(feature = "otlp")]
+    if !otlp::flush() {
+        failures.push("otlp: timed out waiting for export".to_string());
+    }
+    if ENABLED_SINKS_STDOUT.load(Ordering::Acquire)
+        && let Err(err) = std::io::stdout().lock().flush()
+    {
+        failures.push(format!("stdout: {err}"));
+    }
+    if ENABLED_SINKS_WRITER_FLAG.load(Ordering::Acquire) {
+        let mut writer = ENABLED_SINKS_WRITER.lock().unwrap_or_else(|handle| {
+            ENABLED_SINKS_WRITER.clear_poison();
+            handle.into_inner()
+        });
+        if let Some(writer) = writer.as_mut()
+            && let Err(err) = writer.flush()
+        {
+            failures.push(format!("writer: {err}"));
+        }
+    }
+    let mut file = ENABLED_SINKS_FILE.lock().unwrap_or_else(|handle| {
+        ENABLED_SINKS_FILE.clear_poison();
+        handle.into_inner()
+    });
+    if let Some(file) = file.as_mut()
+        && let Err(err) = file.flush()
+    {
+        failures.push(format!("file: {err}"));
+    }
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "Failed to flush log sinks: {}",
+            failures.join("; ")
+        ))
+    }
+}
+
+/// Flushes the sinks on a background thread at least every `interval`, so that
+/// records reach their destination in bounded time even without explicit calls
+/// to `flush`. `None` disables periodic flushing, which is the default.
+pub fn set_flush_interval(interval: Option<Duration>) {
+    let interval_ms = interval.map_or(0, |interval| (interval.as_millis() as u64).max(1));
+    FLUSH_INTERVAL_MS.store(interval_ms, Ordering::Release);
+    if interval.is_none() && FLUSH_THREAD.get().is_none() {
+        return;
+    }
+    FLUSH_THREAD
+        .get_or_init(|| {
+            std::thread::Builder::new()
+                .name("zlog-flush-interval".into())
+                .spawn(run_periodic_flush)
+                .expect("Failed to spawn log flush thread")
+                .thread()
+                .clone()
+        })
+        .unpark();
+}
+
+fn flush_interval() -> Option<Duration> {
+    match FLUSH_INTERVAL_MS.load(Ordering::Acquire) {
+        0 => None,
+        interval_ms => Some(Duration::from_millis(interval_ms)),
+    }
+}
+
+fn run_periodic_flush() {
+    let mut periodic_flush = PeriodicFlush::new(Instant::now());
+    loop {
+        match flush_interval() {
+            Some(interval) => {
+                let wait = periodic_flush.tick(Instant::now(), interval, flush);
+                std::thread::park_timeout(wait);
+            }
+            None => {
+                std::thread::park();
+                periodic_flush = PeriodicFlush::new(Instant::now());
+            }
+        }
+    }
+}
+
+/// Tracks when the sinks were last flushed by the periodic flush thread.
+struct PeriodicFlush {
+    last_flush: Instant,
+}
+
+impl PeriodicFlush {
+    fn new(now: Instant) -> Self {
+        Self { last_flush: now }
+    }
+
+    /// Calls `flush` if at least `interval` has passed since the last flush, and
+    /// returns how long to wait until the next one is due.
+    fn tick(&mut self, now: Instant, interval: Duration, flush: impl FnOnce()) -> Duration {
+        let elapsed = now.saturating_duration_since(self.last_flush);
+        if elapsed < interval {
+            return interval - elapsed;
+        }
+        flush();
+        self.last_flush = now;
+        interval
+    }
+}
+
+/// Like `flush`, but gives up waiting after `timeout` so that shutdown paths
+/// don't hang on a stuck sink. Returns whether everything was written out in
+/// time; if not, the flush carries on in the background.
+pub fn flush_timeout(timeout: Duration) -> bool {
+    run_with_timeout(timeout, flush)
+}
+
+fn run_with_timeout(timeout: Duration, f: impl FnOnce() + Send + 'static) -> bool {
+    let (done_tx, done_rx) = std::sync::mpsc::channel();
+    let spawned = std::thread::Builder::new()
+        .name("zlog-flush".into())
+        .spawn(move || {
+            f();
+            _ = done_tx.send(());
+        });
+    if let Err(err) = spawned {
+        eprintln!("Failed to spawn log flush thread: {err}");
+        return false;
+    }
+    done_rx.recv_timeout(timeout).is_ok()
+}
+
+struct SourceFmt<'a> {
+    scope: ScopeRef<'a>,
+    module_path: Option<&'a str>,
+    line: Option<u32>,
+    ansi: bool,
+}
+
+impl std::fmt::Display for SourceFmt<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use std::fmt::Write;
         f.write_char('[')?;
         if self.ansi {
@@ -257,9 +1447,220 @@ impl std::fmt::Display for SourceFmt<'_> {
     }
 }
 
+/// The name of the running executable, which external sinks use to identify
+/// where records came from.
+#[cfg(any(target_os = "linux", feature = "otlp"))]
+fn process_name() -> &'static str {
+    static PROCESS_NAME: OnceLock<String> = OnceLock::new();
+    PROCESS_NAME.get_or_init(|| {
+        std::env::args_os()
+            .next()
+            .and_then(|arg| {
+                std::path::Path::new(&arg)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+            })
+            .unwrap_or_else(|| env!("CARGO_PKG_NAME").to_string())
+    })
+}
+
+/// The record's scope joined with `.`, or its module path when it has no
+/// scope of its own, as shown in the text output.
+fn scope_name(record: &Record) -> String {
+    if (record.scope[1].is_empty() && record.module_path.is_some()) || record.scope[0].is_empty() {
+        return record.module_path.unwrap_or("?").to_string();
+    }
+    let mut scope = String::from(record.scope[0]);
+    for subscope in record.scope[1..].iter().take_while(|s| !s.is_empty()) {
+        scope.push(SCOPE_STRING_SEP_CHAR);
+        scope.push_str(subscope);
+    }
+    scope
+}
+
+mod config;
+#[cfg(feature = "otlp")]
+mod otlp;
+#[cfg(feature = "otlp")]
+mod queue;
+pub use config::{
+    Rotation, SinkConfig, SinkSnapshot, SinkTarget, config_snapshot, configure,
+    has_configured_target,
+};
+#[cfg(feature = "otlp")]
+pub use otlp::{init_output_otlp, otlp_dropped_count};
+#[cfg(feature = "otlp")]
+pub use queue::OverflowPolicy;
+
+#[cfg(target_os = "linux")]
+pub use journald::init_output_journald;
+
+#[cfg(target_os = "linux")]
+mod journald {
+    use std::{io, os::unix::net::UnixDatagram, sync::OnceLock};
+
+    use super::{Record, process_name, scope_name};
+
+    const JOURNALD_SOCKET_PATH: &str = "/run/systemd/journal/socket";
+
+    /// Is set if journald output is enabled.
+    static ENABLED_SINKS_JOURNALD: OnceLock<UnixDatagram> = OnceLock::new();
+
+    /// Sends records to the systemd journal over its native protocol, as
+    /// structured entries with a `PRIORITY`, `SYSLOG_IDENTIFIER` and `SCOPE`
+    /// rather than formatted text lines.
+    pub fn init_output_journald() -> io::Result<()> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(JOURNALD_SOCKET_PATH)?;
+        _ = ENABLED_SINKS_JOURNALD.set(socket);
+        Ok(())
+    }
+
+    pub(super) fn submit(record: &Record) {
+        let Some(socket) = ENABLED_SINKS_JOURNALD.get() else {
+            return;
+        };
+        _ = socket.send(&encode_fields(&fields(record)));
+    }
+
+    fn fields(record: &Record) -> Vec<(&'static str, String)> {
+        let mut fields = vec![
+            ("PRIORITY", super::severity_of(record.level).to_string()),
+            ("SYSLOG_IDENTIFIER", process_name().to_string()),
+            ("SCOPE", scope_name(record)),
+            ("MESSAGE", record.message.to_string()),
+        ];
+        if let Some(line) = record.line {
+            fields.push(("CODE_LINE", line.to_string()));
+        }
+        fields
+    }
+
+    /// Encodes `fields` as a journal native protocol datagram. Values that
+    /// contain newlines use the length-prefixed binary form.
+    fn encode_fields(fields: &[(&'static str, String)]) -> Vec<u8> {
+        let mut payload = Vec::new();
+        for (key, value) in fields {
+            payload.extend_from_slice(key.as_bytes());
+            if value.contains('\n') {
+                payload.push(b'\n');
+                payload.extend_from_slice(&(value.len() as u64).to_le_bytes());
+            } else {
+                payload.push(b'=');
+            }
+            payload.extend_from_slice(value.as_bytes());
+            payload.push(b'\n');
+        }
+        payload
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_journald_fields() {
+            let record = Record {
+                scope: ["zlog", "journald", "", ""],
+                level: log::Level::Warn,
+                message: &format_args!("disk {}", "full"),
+                module_path: Some("zlog::sink"),
+                line: None,
+                seq: 0,
+            };
+            let fields = fields(&record);
+            assert_eq!(
+                fields,
+                [
+                    ("PRIORITY", "4".to_string()),
+                    ("SYSLOG_IDENTIFIER", process_name().to_string()),
+                    ("SCOPE", "zlog.journald".to_string()),
+                    ("MESSAGE", "disk full".to_string()),
+                ]
+            );
+            assert!(!process_name().is_empty());
+
+            let payload = encode_fields(&[
+                ("PRIORITY", "3".to_string()),
+                ("MESSAGE", "two\nlines".to_string()),
+            ]);
+            let mut expected = b"PRIORITY=3\nMESSAGE\n".to_vec();
+            expected.extend_from_slice(&9u64.to_le_bytes());
+            expected.extend_from_slice(b"two\nlines\n");
+            assert_eq!(payload, expected);
+        }
+    }
+}
+
+struct ContextFmt<'a>(&'a [(&'static str, String)]);
+
+impl std::fmt::Display for ContextFmt<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (key, value) in self.0 {
+            write!(f, " {key}={value}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Displays a logfmt value, quoted if it's empty or contains spaces, `=`, `"` or
+/// control characters, which are escaped along with `\` inside the quotes.
+struct LogfmtValue<'a>(&'a str);
+
+impl std::fmt::Display for LogfmtValue<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use std::fmt::Write;
+        let needs_quotes = self.0.is_empty()
+            || self
+                .0
+                .chars()
+                .any(|c| c == ' ' || c == '=' || c == '"' || c.is_control());
+        if !needs_quotes {
+            return f.write_str(self.0);
+        }
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            match c {
+                '"' | '\\' => {
+                    f.write_char('\\')?;
+                    f.write_char(c)?;
+                }
+                c if c.is_control() => write!(f, "{}", c.escape_debug())?,
+                c => f.write_char(c)?,
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+/// Displays bytes that may not be valid UTF-8, such as an `OsStr` from
+/// `as_encoded_bytes`, without losing information: valid UTF-8 is written as
+/// is, except that `\` is escaped as `\\`, and every other byte is written as
+/// `\xNN`. Records are always text, so this is how binary data should be
+/// logged.
+pub struct EscapedBytes<'a>(pub &'a [u8]);
+
+impl std::fmt::Display for EscapedBytes<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for chunk in self.0.utf8_chunks() {
+            for (ix, part) in chunk.valid().split('\\').enumerate() {
+                if ix > 0 {
+                    f.write_str("\\\\")?;
+                }
+                f.write_str(part)?;
+            }
+            for byte in chunk.invalid() {
+                write!(f, "\\x{byte:02x}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
 fn rotate_log_file<PathRef>(
     path: Option<PathRef>,
     path_rotate: Option<PathRef>,
+    compress: bool,
 ) -> std::io::Result<Option<fs::File>>
 where
     PathRef: AsRef<std::path::Path>,
@@ -268,9 +1669,20 @@ where
     let rotation_error = match (path, path_rotate) {
         (Some(_), None) => Some(anyhow::anyhow!("No rotation log file path configured")),
         (None, _) => Some(anyhow::anyhow!("No log file path configured")),
-        (Some(path), Some(path_rotate)) => fs::copy(path, path_rotate)
-            .err()
-            .map(|err| anyhow::anyhow!(err)),
+        (Some(path), Some(path_rotate)) => match fs::copy(path, &path_rotate) {
+            Ok(_) => {
+                if compress {
+                    let path_rotate = path_rotate.as_ref().to_path_buf();
+                    std::thread::spawn(move || {
+                        if let Err(err) = compress_rotated_log_file(&path_rotate) {
+                            eprintln!("Failed to compress rotated log file: {err}");
+                        }
+                    });
+                }
+                None
+            }
+            Err(err) => Some(anyhow::anyhow!(err)),
+        },
     };
     if let Some(err) = rotation_error {
         eprintln!("Log file rotation failed. Truncating log file anyways: {err}",);
@@ -285,6 +1697,63 @@ where
     .transpose()
 }
 
+/// Compresses `path_rotate` to `<path_rotate>.gz`.
+///
+/// The compressed output is written to a temporary file that is renamed into
+/// place once complete, so a crash mid-compression leaves the uncompressed
+/// rotated file intact rather than a truncated archive.
+fn compress_rotated_log_file(path_rotate: &std::path::Path) -> io::Result<()> {
+    static COMPRESSING: Mutex<()> = Mutex::new(());
+    let _guard = COMPRESSING.lock().unwrap_or_else(|handle| {
+        COMPRESSING.clear_poison();
+        handle.into_inner()
+    });
+
+    let mut path_compressed = path_rotate.as_os_str().to_owned();
+    path_compressed.push(".gz");
+    let path_compressed = PathBuf::from(path_compressed);
+    let mut path_compressed_tmp = path_compressed.as_os_str().to_owned();
+    path_compressed_tmp.push(".tmp");
+    let path_compressed_tmp = PathBuf::from(path_compressed_tmp);
+
+    let mut input = fs::File::open(path_rotate)?;
+    let mut encoder = flate2::write::GzEncoder::new(
+        fs::File::create(&path_compressed_tmp)?,
+        flate2::Compression::default(),
+    );
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+    fs::rename(&path_compressed_tmp, &path_compressed)?;
+    fs::remove_file(path_rotate)
+}
+
+/// Everything written to the sinks since the first call, which installs a
+/// custom writer that captures output. Tests should look for their own records,
+/// as other tests may log concurrently.
+#[cfg(test)]
+pub(crate) fn captured_output() -> String {
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    static CAPTURED: OnceLock<SharedBuffer> = OnceLock::new();
+    let buffer = CAPTURED.get_or_init(|| {
+        let buffer = SharedBuffer::default();
+        init_output_writer(Box::new(buffer.clone()));
+        buffer
+    });
+    String::from_utf8_lossy(&buffer.0.lock().unwrap()).into_owned()
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -299,7 +1768,13 @@ mod tests {
         let contents = String::from("Hello, world!");
         std::fs::write(&log_file_path, &contents).unwrap();
 
-        open_or_create_log_file(&log_file_path, Some(&rotation_log_file_path), 4).unwrap();
+        open_or_create_log_file(
+            &log_file_path,
+            Some(&rotation_log_file_path),
+            OpenMode::Append,
+            4,
+        )
+        .unwrap();
 
         assert!(log_file_path.exists());
         assert_eq!(log_file_path.metadata().unwrap().len(), 0);
@@ -307,6 +1782,57 @@ mod tests {
         assert_eq!(std::fs::read_to_string(&log_file_path).unwrap(), "");
     }
 
+    #[test]
+    fn test_open_or_create_log_file_modes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_file_path = temp_dir.path().join("log.txt");
+
+        std::fs::write(&log_file_path, "existing\n").unwrap();
+        let mut file = open_or_create_log_file(&log_file_path, None, OpenMode::Append, !0).unwrap();
+        file.write_all(b"appended\n").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&log_file_path).unwrap(),
+            "existing\nappended\n"
+        );
+
+        std::fs::write(&log_file_path, "existing\n").unwrap();
+        let mut file =
+            open_or_create_log_file(&log_file_path, None, OpenMode::Truncate, !0).unwrap();
+        file.write_all(b"fresh\n").unwrap();
+        assert_eq!(std::fs::read_to_string(&log_file_path).unwrap(), "fresh\n");
+    }
+
+    #[test]
+    fn test_rotate_log_file_compressed() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_file_path = temp_dir.path().join("log.txt");
+        let rotation_log_file_path = temp_dir.path().join("log.txt.1");
+        let compressed_log_file_path = temp_dir.path().join("log.txt.1.gz");
+
+        let contents = String::from("Hello, world!\n").repeat(100);
+        std::fs::write(&log_file_path, &contents).unwrap();
+
+        rotate_log_file(Some(&log_file_path), Some(&rotation_log_file_path), true).unwrap();
+        assert_eq!(log_file_path.metadata().unwrap().len(), 0);
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while rotation_log_file_path.exists() {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "rotated log file was not compressed"
+            );
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        let mut decompressed = String::new();
+        io::Read::read_to_string(
+            &mut flate2::read::GzDecoder::new(fs::File::open(&compressed_log_file_path).unwrap()),
+            &mut decompressed,
+        )
+        .unwrap();
+        assert_eq!(decompressed, contents);
+    }
+
     #[test]
     fn test_open_or_create_log_file() {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -316,7 +1842,13 @@ mod tests {
         let contents = String::from("Hello, world!");
         std::fs::write(&log_file_path, &contents).unwrap();
 
-        open_or_create_log_file(&log_file_path, Some(&rotation_log_file_path), !0).unwrap();
+        open_or_create_log_file(
+            &log_file_path,
+            Some(&rotation_log_file_path),
+            OpenMode::Append,
+            !0,
+        )
+        .unwrap();
 
         assert!(log_file_path.exists());
         assert_eq!(log_file_path.metadata().unwrap().len(), 13);
@@ -324,6 +1856,739 @@ mod tests {
         assert_eq!(std::fs::read_to_string(&log_file_path).unwrap(), contents);
     }
 
+    #[test]
+    fn test_init_output_writer() {
+        captured_output();
+        submit(Record {
+            scope: ["zlog", "writer", "", ""],
+            level: log::Level::Warn,
+            message: &format_args!("written to a custom writer"),
+            module_path: None,
+            line: None,
+            seq: 0,
+        });
+        flush();
+
+        let output = captured_output();
+        let line = output
+            .lines()
+            .find(|line| line.ends_with("written to a custom writer"))
+            .expect("record was written to the custom writer");
+        assert!(line.contains(" WARN  [zlog.writer] "), "{line}");
+        assert!(!line.contains(ANSI_RESET));
+    }
+
+    #[test]
+    fn test_format_record_matches_writer() {
+        let record = Record {
+            scope: ["zlog", "format_record", "", ""],
+            level: log::Level::Error,
+            message: &format_args!("formatted like the writer"),
+            module_path: Some("zlog::sink"),
+            line: Some(42),
+            seq: 0,
+        };
+        let formatted = format_record(&record);
+        captured_output();
+        submit(Record { ..record });
+        flush();
+
+        let output = captured_output();
+        let written = output
+            .lines()
+            .find(|line| line.ends_with("formatted like the writer"))
+            .expect("record was written to the custom writer");
+        // The timestamps differ, so only compare what follows them.
+        let without_timestamp = |line: &str| line.split_once(' ').unwrap().1.to_string();
+        assert_eq!(without_timestamp(&formatted), without_timestamp(written));
+        assert_eq!(
+            without_timestamp(&formatted),
+            "ERROR [zlog.format_record] formatted like the writer"
+        );
+    }
+
+    #[test]
+    fn test_flush_timeout() {
+        struct SlowWriter(Duration);
+
+        impl Write for SlowWriter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                std::thread::sleep(self.0);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                std::thread::sleep(self.0);
+                Ok(())
+            }
+        }
+
+        assert!(flush_timeout(Duration::from_secs(10)));
+
+        let (flushed_tx, flushed_rx) = std::sync::mpsc::channel();
+        let mut writer = SlowWriter(Duration::from_millis(200));
+        let completed = run_with_timeout(Duration::from_millis(10), move || {
+            _ = writer.write_all(b"slow record\n");
+            _ = writer.flush();
+            flushed_tx.send(()).unwrap();
+        });
+        assert!(!completed);
+        flushed_rx
+            .recv_timeout(Duration::from_secs(10))
+            .expect("slow flush eventually completes");
+    }
+
+    #[test]
+    fn test_periodic_flush() {
+        let interval = Duration::from_secs(5);
+        let start = Instant::now();
+        let mut periodic_flush = PeriodicFlush::new(start);
+        let mut flushes = 0;
+
+        let wait = periodic_flush.tick(start + Duration::from_secs(2), interval, || flushes += 1);
+        assert_eq!(flushes, 0);
+        assert_eq!(wait, Duration::from_secs(3));
+
+        let wait = periodic_flush.tick(start + Duration::from_secs(5), interval, || flushes += 1);
+        assert_eq!(flushes, 1);
+        assert_eq!(wait, interval);
+
+        let wait = periodic_flush.tick(start + Duration::from_secs(6), interval, || flushes += 1);
+        assert_eq!(flushes, 1);
+        assert_eq!(wait, Duration::from_secs(4));
+    }
+
+    #[test]
+    fn test_severity_map() {
+        use log::Level;
+
+        let levels = [
+            Level::Error,
+            Level::Warn,
+            Level::Info,
+            Level::Debug,
+            Level::Trace,
+        ];
+        assert_eq!(SEVERITY_MAP_DEFAULT, [3, 4, 6, 7, 7]);
+
+        // Warn keeps its default, since other tests submit warnings concurrently.
+        set_severity_map([2, 4, 5, 6, 7]);
+        assert_eq!(levels.map(severity_of), [2, 4, 5, 6, 7]);
+        set_severity_map(SEVERITY_MAP_DEFAULT);
+        assert_eq!(levels.map(severity_of), SEVERITY_MAP_DEFAULT);
+    }
+
+    #[test]
+    fn test_record_batch_boundary() {
+        let mut batch = RecordBatch::default();
+        let mut written = Vec::new();
+        for i in 0..5 {
+            batch.push(
+                &Record {
+                    scope: ["zlog", "", "", ""],
+                    level: log::Level::Info,
+                    message: &format_args!("record {i}"),
+                    module_path: None,
+                    line: None,
+                    seq: 0,
+                },
+                None,
+                true,
+            );
+            if batch.len >= 2 {
+                batch.drain(|_, _, file| written.extend_from_slice(file));
+            }
+        }
+        assert_eq!(batch.len, 1);
+        batch.drain(|_, _, file| written.extend_from_slice(file));
+        assert_eq!(batch.len, 0);
+
+        let written = String::from_utf8(written).unwrap();
+        let messages = written
+            .lines()
+            .map(|line| line.split_once("[zlog] ").unwrap().1)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            ["record 0", "record 1", "record 2", "record 3", "record 4"]
+        );
+    }
+
+    fn fixed_instant() -> chrono::DateTime<chrono::Utc> {
+        chrono::NaiveDate::from_ymd_opt(2024, 3, 9)
+            .unwrap()
+            .and_hms_milli_opt(14, 5, 6, 789)
+            .unwrap()
+            .and_utc()
+    }
+
+    #[test]
+    fn test_timestamp_format() {
+        let time = fixed_instant();
+
+        assert_eq!(
+            format_timestamp(&time, Timezone::Utc, "%H:%M:%S%.3f").to_string(),
+            "14:05:06.789"
+        );
+        assert_eq!(
+            format_timestamp(&time, Timezone::Utc, "%Y/%m/%d").to_string(),
+            "2024/03/09"
+        );
+
+        assert!(set_timestamp_format("%H:%Q").is_err());
+        assert_eq!(*TIMESTAMP_FORMAT.read().unwrap(), TIMESTAMP_FORMAT_DEFAULT);
+    }
+
+    #[test]
+    fn test_timestamp_timezone() {
+        let time = fixed_instant();
+
+        assert_eq!(
+            format_timestamp(&time, Timezone::Utc, TIMESTAMP_FORMAT_DEFAULT).to_string(),
+            "2024-03-09T14:05:06.789+00:00"
+        );
+
+        let local = time.with_timezone(&chrono::Local);
+        assert_eq!(
+            format_timestamp(&time, Timezone::Local, TIMESTAMP_FORMAT_DEFAULT).to_string(),
+            format!(
+                "{}{}",
+                local.naive_local().format("%Y-%m-%dT%H:%M:%S%.3f"),
+                local.offset()
+            )
+        );
+
+        assert_eq!(timestamp_timezone(), Timezone::Local);
+    }
+
+    #[test]
+    fn test_timestamp_rfc3339() {
+        let time = fixed_instant();
+        let format = TimestampFormat::Rfc3339.strftime();
+
+        assert_eq!(
+            format_timestamp(&time, Timezone::Utc, format).to_string(),
+            "2024-03-09T14:05:06.789+00:00"
+        );
+        let offset = chrono::FixedOffset::east_opt(2 * 3600).unwrap();
+        assert_eq!(
+            format_timestamp(&time, Timezone::Fixed(offset), format).to_string(),
+            "2024-03-09T16:05:06.789+02:00"
+        );
+        let offset = chrono::FixedOffset::west_opt(9 * 3600 + 30 * 60).unwrap();
+        assert_eq!(
+            format_timestamp(&time, Timezone::Fixed(offset), format).to_string(),
+            "2024-03-09T04:35:06.789-09:30"
+        );
+        assert!(
+            chrono::DateTime::parse_from_rfc3339(
+                &format_timestamp(&time, Timezone::Local, format).to_string()
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_console_output_split() {
+        let mut batch = RecordBatch::default();
+        for level in [
+            log::Level::Trace,
+            log::Level::Debug,
+            log::Level::Info,
+            log::Level::Warn,
+            log::Level::Error,
+        ] {
+            batch.push(
+                &Record {
+                    scope: ["zlog", "split", "", ""],
+                    level,
+                    message: &format_args!("{level} record"),
+                    module_path: None,
+                    line: None,
+                    seq: 0,
+                },
+                Some(ConsoleOutput::Split),
+                false,
+            );
+        }
+
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        batch.drain(|console, console_stderr, _| {
+            stdout.extend_from_slice(console);
+            stderr.extend_from_slice(console_stderr);
+        });
+        let messages = |output: Vec<u8>| {
+            String::from_utf8(output)
+                .unwrap()
+                .lines()
+                .map(|line| line.rsplit_once("] ").unwrap().1.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            messages(stdout),
+            ["TRACE record", "DEBUG record", "INFO record"]
+        );
+        assert_eq!(messages(stderr), ["WARN record", "ERROR record"]);
+    }
+
+    #[test]
+    fn test_instance_tag() {
+        let record = Record {
+            scope: ["zlog", "instance", "", ""],
+            level: log::Level::Info,
+            message: &format_args!("tagged"),
+            module_path: None,
+            line: None,
+            seq: 0,
+        };
+        let mut output = Vec::new();
+        for ansi in [false, true] {
+            write_record(
+                &mut output,
+                &record,
+                OutputFormat::Text,
+                false,
+                Some("pid-42"),
+                None,
+                &"2024",
+                &[],
+                ansi,
+            );
+        }
+        write_record(
+            &mut output,
+            &record,
+            OutputFormat::Text,
+            false,
+            None,
+            None,
+            &"2024",
+            &[],
+            false,
+        );
+
+        let output = String::from_utf8(output).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        for line in &lines[..2] {
+            assert!(line.starts_with("pid-42 2024 "), "{line}");
+            assert_eq!(line.matches("pid-42").count(), 1, "{line}");
+        }
+        assert!(lines[2].starts_with("2024 "), "{}", lines[2]);
+    }
+
+    #[test]
+    fn test_subscribe() {
+        let _lock = crate::filter::REFRESH_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        let (tx, rx) = std::sync::mpsc::channel();
+        let id = subscribe(Box::new(move |record: &OwnedRecord| {
+            if record.scope[1] == "subscribe_test" {
+                _ = tx.send(record.message.clone());
+            }
+        }));
+        crate::info!(crate::scoped!("subscribe_test") => "first");
+        assert_eq!(rx.try_recv().as_deref(), Ok("first"));
+
+        unsubscribe(id);
+        crate::info!(crate::scoped!("subscribe_test") => "second");
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_log_banner() {
+        captured_output();
+        log_banner("banner_test_app", "1.2.3+abc1234");
+        flush();
+
+        let expected = format!(
+            "banner_test_app 1.2.3+abc1234 ({} {})",
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        );
+        let output = captured_output();
+        let line = output
+            .lines()
+            .find(|line| line.contains(&expected))
+            .expect("banner was logged");
+        assert!(line.contains(" INFO "), "{line}");
+
+        let mut file = Vec::new();
+        let written = write_banner(&mut file);
+        assert_eq!(written, file.len() as u64);
+        let file = String::from_utf8(file).unwrap();
+        assert!(file.ends_with(&format!("{expected}\n")), "{file}");
+        assert_eq!(file.lines().count(), 1);
+    }
+
+    #[test]
+    fn test_write_with_fallback() {
+        struct FailingWriter;
+
+        impl Write for FailingWriter {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::other("disk full"))
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        #[derive(Clone, Default)]
+        struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+        impl Write for SharedBuffer {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let fallback = SharedBuffer::default();
+        init_output_fallback(Box::new(fallback.clone()));
+        write_with_fallback(&mut Vec::new(), b"written to primary\n");
+        write_with_fallback(&mut FailingWriter, b"first lost record\n");
+        write_with_fallback(&mut FailingWriter, b"second lost record\n");
+
+        let output = String::from_utf8(fallback.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(
+            output,
+            "Failed to write to log output, writing records here instead: disk full\n\
+             first lost record\n\
+             second lost record\n"
+        );
+    }
+
+    #[test]
+    fn test_seq_increases_across_threads() {
+        let threads = (0..4)
+            .map(|_| {
+                std::thread::spawn(|| {
+                    (0..100)
+                        .map(|_| {
+                            let mut record = Record {
+                                scope: ["zlog", "seq", "", ""],
+                                level: log::Level::Info,
+                                message: &format_args!("sequenced"),
+                                module_path: None,
+                                line: None,
+                                seq: 0,
+                            };
+                            assign_seq(&mut record);
+                            record.seq
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut all_seqs = Vec::new();
+        for thread in threads {
+            let seqs = thread.join().unwrap();
+            assert!(seqs.is_sorted_by(|a, b| a < b), "{seqs:?}");
+            all_seqs.extend(seqs);
+        }
+        all_seqs.sort_unstable();
+        all_seqs.dedup();
+        assert_eq!(all_seqs.len(), 400);
+        assert!(all_seqs[0] > 0);
+
+        let record = Record {
+            scope: ["zlog", "seq", "", ""],
+            level: log::Level::Info,
+            message: &format_args!("sequenced"),
+            module_path: None,
+            line: None,
+            seq: 42,
+        };
+        let mut output = Vec::new();
+        write_record(
+            &mut output,
+            &record,
+            OutputFormat::Text,
+            false,
+            Some("pid-42"),
+            Some(record.seq),
+            &"2024",
+            &[],
+            false,
+        );
+        assert!(output.starts_with(b"pid-42 #42 2024 "));
+        assert_eq!(record.to_owned().seq, 42);
+    }
+
+    #[test]
+    fn test_escaped_bytes() {
+        fn unescape(escaped: &str) -> Vec<u8> {
+            let mut bytes = Vec::new();
+            let mut rest = escaped.as_bytes();
+            while let Some((&byte, tail)) = rest.split_first() {
+                match (byte, tail) {
+                    (b'\\', [b'\\', tail @ ..]) => {
+                        bytes.push(b'\\');
+                        rest = tail;
+                    }
+                    (b'\\', [b'x', hi, lo, tail @ ..]) => {
+                        let hex = std::str::from_utf8(&[*hi, *lo]).unwrap().to_owned();
+                        bytes.push(u8::from_str_radix(&hex, 16).unwrap());
+                        rest = tail;
+                    }
+                    _ => {
+                        bytes.push(byte);
+                        rest = tail;
+                    }
+                }
+            }
+            bytes
+        }
+
+        let raw: &[u8] = b"C:\\data\\\xff\xfe caf\xc3\xa9 \xc3";
+        let escaped = EscapedBytes(raw).to_string();
+        assert_eq!(escaped, "C:\\\\data\\\\\\xff\\xfe caf\u{e9} \\xc3");
+        assert_eq!(unescape(&escaped), raw);
+
+        captured_output();
+        submit(Record {
+            scope: ["zlog", "escaped_bytes", "", ""],
+            level: log::Level::Error,
+            message: &format_args!("read {}", EscapedBytes(raw)),
+            module_path: None,
+            line: None,
+            seq: 0,
+        });
+        flush();
+        let output = captured_output();
+        let line = output
+            .lines()
+            .find(|line| line.contains("zlog.escaped_bytes"))
+            .expect("record was written");
+        let (_, message) = line.split_once("read ").unwrap();
+        assert_eq!(unescape(message), raw);
+    }
+
+    #[test]
+    fn test_logfmt() {
+        let record = Record {
+            scope: ["zlog", "logfmt", "", ""],
+            level: log::Level::Warn,
+            message: &format_args!("he said \"hi\" twice"),
+            module_path: None,
+            line: None,
+            seq: 3,
+        };
+        let mut output = Vec::new();
+        write_record(
+            &mut output,
+            &record,
+            OutputFormat::Logfmt,
+            false,
+            None,
+            Some(record.seq),
+            &"2024-01-02T03:04:05",
+            &[("path", "a=b c".to_string()), ("id", "7".to_string())],
+            true,
+        );
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "ts=2024-01-02T03:04:05 level=warn scope=zlog.logfmt seq=3 \
+             msg=\"he said \\\"hi\\\" twice\" path=\"a=b c\" id=7\n"
+        );
+
+        for (value, expected) in [
+            ("plain", "plain"),
+            ("", "\"\""),
+            ("back\\slash", "back\\slash"),
+            ("two\nlines", "\"two\\nlines\""),
+            ("C:\\a b", "\"C:\\\\a b\""),
+        ] {
+            assert_eq!(LogfmtValue(value).to_string(), expected, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn test_context_is_rendered() {
+        let record = Record {
+            scope: ["zlog", "context", "", ""],
+            level: log::Level::Info,
+            message: &format_args!("handling request"),
+            module_path: None,
+            line: None,
+            seq: 0,
+        };
+
+        let mut batch = RecordBatch::default();
+        {
+            let _guard = crate::context::scoped("request_id", "42");
+            batch.push(&record, Some(ConsoleOutput::Stdout), true);
+        }
+        batch.push(&record, Some(ConsoleOutput::Stdout), true);
+
+        let file = String::from_utf8(batch.file).unwrap();
+        let lines = file.lines().collect::<Vec<_>>();
+        assert!(lines[0].ends_with("handling request request_id=42"));
+        assert!(lines[1].ends_with("handling request"));
+        let console = String::from_utf8(batch.console).unwrap();
+        assert!(console.lines().next().unwrap().ends_with(" request_id=42"));
+    }
+
+    #[test]
+    fn test_owned_record_round_trip() {
+        let render = |record: &Record| {
+            let mut batch = RecordBatch::default();
+            batch.push(record, Some(ConsoleOutput::Stdout), true);
+            let strip_timestamp = |rendered: &[u8]| {
+                let rendered = String::from_utf8(rendered.to_vec()).unwrap();
+                rendered.split_once(' ').unwrap().1.to_string()
+            };
+            (
+                strip_timestamp(&batch.console),
+                strip_timestamp(&batch.file),
+            )
+        };
+
+        let record = Record {
+            scope: ["zlog", "owned", "", ""],
+            level: log::Level::Warn,
+            message: &format_args!("{} records in {:?}", 3, "flight"),
+            module_path: Some("zlog/src/sink.rs"),
+            line: Some(42),
+            seq: 0,
+        };
+        let owned = record.to_owned();
+        assert_eq!(owned.scope, ["zlog", "owned", "", ""].map(String::from));
+        assert_eq!(owned.message, "3 records in \"flight\"");
+        assert_eq!(owned, OwnedRecord::from(&record));
+
+        let owned = std::thread::spawn(move || owned).join().unwrap();
+        assert_eq!(owned.with_record(|record| render(&record)), render(&record));
+    }
+
+    #[test]
+    fn test_auto_colors() {
+        let dumb = Some("dumb".as_ref());
+        let xterm = Some("xterm-256color".as_ref());
+        assert!(auto_colors(true, None, xterm));
+        assert!(auto_colors(true, None, None));
+        assert!(!auto_colors(false, None, xterm));
+
+        assert!(!auto_colors(true, Some("1".as_ref()), xterm));
+        assert!(!auto_colors(true, Some("true".as_ref()), None));
+        assert!(auto_colors(true, Some("".as_ref()), xterm));
+
+        assert!(!auto_colors(true, None, dumb));
+        assert!(!auto_colors(true, Some("1".as_ref()), dumb));
+    }
+
+    #[test]
+    fn test_newline_modes() {
+        let records = RenderedRecords {
+            bytes: b"first\nmulti\nline\n",
+            ends: &[6, 17],
+        };
+        let written = |mode| {
+            let mut writes = Vec::new();
+            let len = records.write(mode, |bytes| writes.push(bytes.to_vec()));
+            assert_eq!(len, writes.iter().map(Vec::len).sum::<usize>());
+            writes
+        };
+        assert_eq!(written(NewlineMode::Lf), [b"first\nmulti\nline\n".to_vec()]);
+        assert_eq!(
+            written(NewlineMode::CrLf),
+            [b"first\r\nmulti\nline\r\n".to_vec()]
+        );
+        assert_eq!(
+            written(NewlineMode::None),
+            [b"first".to_vec(), b"multi\nline".to_vec()]
+        );
+
+        let mut batch = RecordBatch::default();
+        for message in ["first", "multi\nline"] {
+            batch.push(
+                &Record {
+                    scope: ["zlog", "newline", "", ""],
+                    level: log::Level::Info,
+                    message: &format_args!("{message}"),
+                    module_path: None,
+                    line: None,
+                    seq: 0,
+                },
+                None,
+                true,
+            );
+        }
+        let mut writes = Vec::new();
+        batch.drain(|_, _, file| {
+            file.write(NewlineMode::None, |bytes| {
+                writes.push(String::from_utf8(bytes.to_vec()).unwrap())
+            });
+        });
+        assert_eq!(writes.len(), 2);
+        assert!(writes[0].ends_with("[zlog.newline] first"), "{writes:?}");
+        assert!(
+            writes[1].ends_with("[zlog.newline] multi\nline"),
+            "{writes:?}"
+        );
+    }
+
+    #[test]
+    fn test_compact_level() {
+        let mut output = Vec::new();
+        for level in [
+            log::Level::Error,
+            log::Level::Warn,
+            log::Level::Info,
+            log::Level::Debug,
+            log::Level::Trace,
+        ] {
+            for ansi in [false, true] {
+                write_record(
+                    &mut output,
+                    &Record {
+                        scope: ["zlog", "compact", "", ""],
+                        level,
+                        message: &format_args!("compact"),
+                        module_path: None,
+                        line: None,
+                        seq: 0,
+                    },
+                    OutputFormat::Text,
+                    true,
+                    None,
+                    None,
+                    &"2024",
+                    &[],
+                    ansi,
+                );
+            }
+        }
+
+        let output = String::from_utf8(output).unwrap();
+        let levels = output
+            .lines()
+            .map(|line| line.split(' ').nth(1).unwrap())
+            .collect::<Vec<_>>();
+        let bold = |color: &str, level: &str| format!("{ANSI_BOLD}{color}{level}{ANSI_RESET}");
+        assert_eq!(
+            levels,
+            [
+                "E".to_string(),
+                bold(ANSI_RED, "E"),
+                "W".to_string(),
+                bold(ANSI_YELLOW, "W"),
+                "I".to_string(),
+                bold(ANSI_GREEN, "I"),
+                "D".to_string(),
+                bold(ANSI_BLUE, "D"),
+                "T".to_string(),
+                bold(ANSI_MAGENTA, "T"),
+            ]
+        );
+    }
+
     /// Regression test, ensuring that if log level values change we are made aware
     #[test]
     fn test_log_level_names() {
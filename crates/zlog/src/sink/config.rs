@@ -0,0 +1,207 @@
+//! A single point for configuring the sinks, for embedders that derive their
+//! logging setup from their own configuration.
+
+use std::{
+    borrow::Cow,
+    path::PathBuf,
+    sync::{
+        RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use serde::{Serialize, Serializer};
+
+use super::{
+    BATCH_SIZE, ColorMode, ENABLED_SINKS_FILE_FLAG, ENABLED_SINKS_WRITER_FLAG, OpenMode,
+    OutputFormat, SINK_FILE_PATH, SINK_FILE_PATH_ROTATE, SINK_FILE_ROTATE_COMPRESS,
+    SINK_FILE_SIZE_BYTES_MAX, TIMESTAMP_FORMAT, Timezone, color_mode, compact_level,
+    console_output, init_output_file, init_output_split, init_output_stderr, init_output_stdout,
+    output_format, set_batch_size, set_color_mode, set_output_format, set_rotate_compression,
+    set_timestamp_format, set_timestamp_timezone, timestamp_timezone, validate_timestamp_format,
+};
+
+/// Is set once `configure` has been called.
+static CONFIGURED: AtomicBool = AtomicBool::new(false);
+/// Is set if `configure` selected where text output goes.
+static TARGET_CONFIGURED: AtomicBool = AtomicBool::new(false);
+/// Held for writing while `configure` applies a config, so that
+/// `config_snapshot` never captures a partially applied one.
+static CONFIG_LOCK: RwLock<()> = RwLock::new(());
+
+/// Where text output is written, for [`SinkConfig::target`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SinkTarget {
+    Stdout,
+    Stderr,
+    /// Warnings and errors go to stderr, everything else to stdout.
+    Split,
+    File {
+        path: PathBuf,
+        mode: OpenMode,
+    },
+}
+
+/// How a [`SinkTarget::File`] is rotated once it reaches its maximum size.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Rotation {
+    /// Where the previous contents of the log file are moved.
+    pub path: PathBuf,
+    /// Whether the rotated file is gzip-compressed.
+    pub compress: bool,
+}
+
+/// The settings applied by [`configure`]. Settings left as `None` keep their
+/// defaults.
+#[derive(Clone, Debug, Default)]
+pub struct SinkConfig {
+    pub target: Option<SinkTarget>,
+    /// A strftime-style format for record timestamps, or a built-in
+    /// `TimestampFormat` converted with `into()`.
+    pub timestamp_format: Option<Cow<'static, str>>,
+    pub timezone: Timezone,
+    pub format: OutputFormat,
+    pub color: ColorMode,
+    /// Rotation of the log file. Files without a rotation path are truncated
+    /// once they reach their maximum size.
+    pub rotation: Option<Rotation>,
+    pub batch_size: Option<usize>,
+}
+
+/// Applies `config` to the sinks. Call this once, before `init`, so that no
+/// record is written with partial settings. `ZED_LOG_OUTPUT` is ignored if
+/// `config` selects a target.
+///
+/// Nothing is applied if the config is invalid, or if the sinks were already
+/// configured.
+pub fn configure(config: SinkConfig) -> anyhow::Result<()> {
+    if let Some(timestamp_format) = &config.timestamp_format {
+        validate_timestamp_format(timestamp_format)?;
+    }
+    let _lock = CONFIG_LOCK.write().unwrap_or_else(|err| {
+        CONFIG_LOCK.clear_poison();
+        err.into_inner()
+    });
+    if CONFIGURED.swap(true, Ordering::AcqRel) {
+        anyhow::bail!("Sinks should only be configured once");
+    }
+
+    if let Some(timestamp_format) = config.timestamp_format {
+        set_timestamp_format(timestamp_format)?;
+    }
+    set_timestamp_timezone(config.timezone);
+    set_output_format(config.format);
+    set_color_mode(config.color);
+    if let Some(batch_size) = config.batch_size {
+        set_batch_size(batch_size);
+    }
+    let rotation = config.rotation.map(|rotation| {
+        set_rotate_compression(rotation.compress);
+        &*Box::leak(Box::new(rotation.path))
+    });
+    let Some(target) = config.target else {
+        return Ok(());
+    };
+    match target {
+        SinkTarget::Stdout => init_output_stdout(),
+        SinkTarget::Stderr => init_output_stderr(),
+        SinkTarget::Split => init_output_split(),
+        SinkTarget::File { path, mode } => {
+            let path = Box::leak(Box::new(path));
+            init_output_file(path, rotation, mode)?;
+        }
+    }
+    TARGET_CONFIGURED.store(true, Ordering::Release);
+    Ok(())
+}
+
+/// Whether `configure` selected where text output goes.
+pub fn has_configured_target() -> bool {
+    TARGET_CONFIGURED.load(Ordering::Acquire)
+}
+
+/// The active sink configuration, for dumping into bug reports.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SinkSnapshot {
+    /// The enabled sinks, out of `stdout`, `stderr`, `split`, `file`, `writer`
+    /// and `otlp`.
+    pub targets: Vec<&'static str>,
+    pub file: Option<PathBuf>,
+    /// Rotation of the log file, if it has a rotation path.
+    pub rotation: Option<Rotation>,
+    /// The size in bytes at which the log file is rotated or truncated.
+    pub rotate_at_bytes: u64,
+    pub format: OutputFormat,
+    pub color: ColorMode,
+    pub compact_level: bool,
+    /// The strftime-style format of record timestamps.
+    pub timestamp_format: String,
+    #[serde(serialize_with = "serialize_timezone")]
+    pub timezone: Timezone,
+    pub batch_size: usize,
+}
+
+fn serialize_timezone<S: Serializer>(
+    timezone: &Timezone,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match timezone {
+        Timezone::Local => serializer.serialize_str("local"),
+        Timezone::Utc => serializer.serialize_str("utc"),
+        Timezone::Fixed(offset) => serializer.collect_str(offset),
+    }
+}
+
+/// Captures the active sink configuration. It's never captured while
+/// `configure` is applying a config.
+pub fn config_snapshot() -> SinkSnapshot {
+    let _lock = CONFIG_LOCK.read().unwrap_or_else(|err| {
+        CONFIG_LOCK.clear_poison();
+        err.into_inner()
+    });
+    let file_enabled = ENABLED_SINKS_FILE_FLAG.load(Ordering::Acquire);
+    let mut targets = Vec::new();
+    match console_output() {
+        Some(super::ConsoleOutput::Stdout) => targets.push("stdout"),
+        Some(super::ConsoleOutput::Stderr) => targets.push("stderr"),
+        Some(super::ConsoleOutput::Split) => targets.push("split"),
+        None => {}
+    }
+    if file_enabled {
+        targets.push("file");
+    }
+    if ENABLED_SINKS_WRITER_FLAG.load(Ordering::Acquire) {
+        targets.push("writer");
+    }
+    #[cfg(feature = "otlp")]
+    if super::otlp::is_enabled() {
+        targets.push("otlp");
+    }
+    SinkSnapshot {
+        targets,
+        file: SINK_FILE_PATH
+            .get()
+            .filter(|_| file_enabled)
+            .map(|path| path.to_path_buf()),
+        rotation: SINK_FILE_PATH_ROTATE
+            .get()
+            .filter(|_| file_enabled)
+            .map(|path| Rotation {
+                path: path.to_path_buf(),
+                compress: SINK_FILE_ROTATE_COMPRESS.load(Ordering::Acquire),
+            }),
+        rotate_at_bytes: SINK_FILE_SIZE_BYTES_MAX,
+        format: output_format(),
+        color: color_mode(),
+        compact_level: compact_level(),
+        timestamp_format: TIMESTAMP_FORMAT
+            .read()
+            .unwrap_or_else(|err| {
+                TIMESTAMP_FORMAT.clear_poison();
+                err.into_inner()
+            })
+            .to_string(),
+        timezone: timestamp_timezone(),
+        batch_size: BATCH_SIZE.load(Ordering::Acquire),
+    }
+}
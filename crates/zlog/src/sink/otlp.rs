@@ -0,0 +1,571 @@
+//! Exports records to an OpenTelemetry collector as OTLP logs, encoded as JSON
+//! over plain HTTP.
+
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::Context as _;
+use serde_json::json;
+
+use super::{
+    Record, instance_tag, process_name,
+    queue::{OverflowPolicy, Queue},
+    scope_name,
+};
+
+/// Maximum number of records sent in a single export request.
+const EXPORT_BATCH_SIZE_MAX: usize = 512;
+/// Maximum time a record waits before the batch containing it is exported.
+const EXPORT_INTERVAL: Duration = Duration::from_secs(1);
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Minimum time between reports of failed exports, so that an unreachable
+/// collector doesn't flood stderr with one report per batch.
+const FAILURE_REPORT_INTERVAL: Duration = Duration::from_secs(60);
+/// Maximum time `flush` waits for queued records to be exported.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(10);
+/// Maximum number of records waiting to be exported, after which the
+/// `OverflowPolicy` applies.
+const EXPORT_QUEUE_CAPACITY: usize = 8 * EXPORT_BATCH_SIZE_MAX;
+
+/// Is set if OTLP output is enabled. Records are handed to the export thread
+/// so that encoding and network IO happen off the logging path.
+static ENABLED_SINKS_OTLP: OnceLock<Arc<Queue<OtlpRecord>>> = OnceLock::new();
+
+struct OtlpRecord {
+    time_unix_nano: u128,
+    level: log::Level,
+    scope: String,
+    /// The segments of the record's scope, for collectors to filter on.
+    scope_segments: Vec<String>,
+    message: String,
+    seq: u64,
+}
+
+struct Endpoint {
+    /// `host:port`
+    authority: String,
+    path: String,
+}
+
+/// Exports records to the OTLP/HTTP collector at `endpoint`, e.g.
+/// `http://localhost:4318`. Records are exported in batches from a background
+/// thread, and `overflow_policy` decides what happens when they are submitted
+/// faster than they can be exported. If `endpoint` has no path, the standard
+/// `/v1/logs` path is used.
+pub fn init_output_otlp(endpoint: &str, overflow_policy: OverflowPolicy) -> anyhow::Result<()> {
+    let endpoint = parse_endpoint(endpoint)?;
+    if ENABLED_SINKS_OTLP.get().is_some() {
+        anyhow::bail!("OTLP output should only be initialized once");
+    }
+    let queue = Arc::new(Queue::new(EXPORT_QUEUE_CAPACITY, overflow_policy));
+    std::thread::Builder::new()
+        .name("zlog-otlp".into())
+        .spawn({
+            let queue = queue.clone();
+            move || run_export(endpoint, &queue)
+        })
+        .context("Failed to spawn OTLP export thread")?;
+    if ENABLED_SINKS_OTLP.set(queue).is_err() {
+        anyhow::bail!("OTLP output should only be initialized once");
+    }
+    Ok(())
+}
+
+/// The number of records that were not exported because the export queue was
+/// full.
+pub fn otlp_dropped_count() -> u64 {
+    ENABLED_SINKS_OTLP.get().map_or(0, |queue| queue.dropped())
+}
+
+/// Waits for the records submitted so far to be exported, returning whether
+/// they were before `FLUSH_TIMEOUT`, in case the collector is unresponsive.
+pub(super) fn flush() -> bool {
+    ENABLED_SINKS_OTLP
+        .get()
+        .is_none_or(|queue| queue.flush(FLUSH_TIMEOUT))
+}
+
+pub(super) fn is_enabled() -> bool {
+    ENABLED_SINKS_OTLP.get().is_some()
+}
+
+pub(super) fn submit(record: &Record) {
+    let Some(queue) = ENABLED_SINKS_OTLP.get() else {
+        return;
+    };
+    queue.push(OtlpRecord::new(record));
+}
+
+impl OtlpRecord {
+    fn new(record: &Record) -> Self {
+        Self {
+            time_unix_nano: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |time| time.as_nanos()),
+            level: record.level,
+            scope: scope_name(record),
+            scope_segments: scope_segments(record),
+            message: record.message.to_string(),
+            seq: record.seq,
+        }
+    }
+}
+
+/// The non-empty slots of the record's scope, starting with the crate name.
+/// Records without a scope of their own get the crate name of their module.
+fn scope_segments(record: &Record) -> Vec<String> {
+    if record.scope[0].is_empty() {
+        let crate_name = record.module_path.map_or(
+            crate::SCOPE_UNKNOWN,
+            crate::private::extract_crate_name_from_module_path,
+        );
+        return vec![crate_name.to_string()];
+    }
+    record
+        .scope
+        .iter()
+        .take_while(|segment| !segment.is_empty())
+        .map(|segment| segment.to_string())
+        .collect()
+}
+
+fn parse_endpoint(endpoint: &str) -> anyhow::Result<Endpoint> {
+    let Some(rest) = endpoint.strip_prefix("http://") else {
+        anyhow::bail!("Unsupported OTLP endpoint {endpoint:?}, expected an http:// URL");
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(index) if index + 1 < rest.len() => (&rest[..index], &rest[index..]),
+        Some(index) => (&rest[..index], "/v1/logs"),
+        None => (rest, "/v1/logs"),
+    };
+    if authority.is_empty() {
+        anyhow::bail!("OTLP endpoint {endpoint:?} has no host");
+    }
+    let authority = if authority.contains(':') {
+        authority.to_string()
+    } else {
+        format!("{authority}:80")
+    };
+    Ok(Endpoint {
+        authority,
+        path: path.to_string(),
+    })
+}
+
+fn run_export(endpoint: Endpoint, queue: &Queue<OtlpRecord>) {
+    let mut failures = FailureReports::default();
+    let mut batch = Vec::new();
+    let mut deadline = Instant::now() + EXPORT_INTERVAL;
+    loop {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        if let Some(record) = queue.pop_timeout(timeout) {
+            batch.push(record);
+            if batch.len() < EXPORT_BATCH_SIZE_MAX {
+                continue;
+            }
+        }
+        if !batch.is_empty() {
+            let request = export_request(&batch, instance_tag());
+            if let Err(err) = post(&endpoint, request.to_string().as_bytes())
+                && let Some(suppressed) = failures.report(Instant::now())
+            {
+                if suppressed == 0 {
+                    eprintln!("Failed to export logs to OTLP collector: {err}");
+                } else {
+                    eprintln!(
+                        "Failed to export logs to OTLP collector: {err} ({suppressed} more failed exports since the last report)"
+                    );
+                }
+            }
+            queue.finish(batch.len());
+            batch.clear();
+        }
+        deadline = Instant::now() + EXPORT_INTERVAL;
+    }
+}
+
+/// Limits failed exports to one report per `FAILURE_REPORT_INTERVAL`.
+#[derive(Default)]
+struct FailureReports {
+    last_report: Option<Instant>,
+    suppressed: u64,
+}
+
+impl FailureReports {
+    /// Records a failed export at `now`, returning the number of failures that
+    /// went unreported since the last report if this one should be reported.
+    fn report(&mut self, now: Instant) -> Option<u64> {
+        if self
+            .last_report
+            .is_some_and(|last_report| now.duration_since(last_report) < FAILURE_REPORT_INTERVAL)
+        {
+            self.suppressed += 1;
+            return None;
+        }
+        self.last_report = Some(now);
+        Some(std::mem::take(&mut self.suppressed))
+    }
+}
+
+/// The OTLP `SeverityNumber` for `level`.
+fn severity_number(level: log::Level) -> u8 {
+    match level {
+        log::Level::Trace => 1,
+        log::Level::Debug => 5,
+        log::Level::Info => 9,
+        log::Level::Warn => 13,
+        log::Level::Error => 17,
+    }
+}
+
+/// Builds an `ExportLogsServiceRequest` in the OTLP JSON encoding, with one
+/// instrumentation scope per distinct record scope. Besides the joined name,
+/// each scope has a `zlog.scope` attribute holding its segments as an array.
+fn export_request(records: &[OtlpRecord], instance_tag: Option<&str>) -> serde_json::Value {
+    let mut scopes: Vec<(&OtlpRecord, Vec<serde_json::Value>)> = Vec::new();
+    for record in records {
+        let log_record = json!({
+            "timeUnixNano": record.time_unix_nano.to_string(),
+            "severityNumber": severity_number(record.level),
+            "severityText": record.level.as_str(),
+            "body": { "stringValue": record.message },
+            "attributes": [{
+                "key": "zlog.seq",
+                "value": { "intValue": record.seq.to_string() },
+            }],
+        });
+        match scopes
+            .iter_mut()
+            .find(|(scope_record, _)| scope_record.scope == record.scope)
+        {
+            Some((_, log_records)) => log_records.push(log_record),
+            None => scopes.push((record, vec![log_record])),
+        }
+    }
+    let mut resource_attributes = vec![json!({
+        "key": "service.name",
+        "value": { "stringValue": process_name() },
+    })];
+    if let Some(instance_tag) = instance_tag {
+        resource_attributes.push(json!({
+            "key": "service.instance.id",
+            "value": { "stringValue": instance_tag },
+        }));
+    }
+    json!({
+        "resourceLogs": [{
+            "resource": { "attributes": resource_attributes },
+            "scopeLogs": scopes
+                .into_iter()
+                .map(|(scope_record, log_records)| json!({
+                    "scope": {
+                        "name": scope_record.scope,
+                        "attributes": [{
+                            "key": "zlog.scope",
+                            "value": {
+                                "arrayValue": {
+                                    "values": scope_record
+                                        .scope_segments
+                                        .iter()
+                                        .map(|segment| json!({ "stringValue": segment }))
+                                        .collect::<Vec<_>>(),
+                                },
+                            },
+                        }],
+                    },
+                    "logRecords": log_records,
+                }))
+                .collect::<Vec<_>>(),
+        }],
+    })
+}
+
+/// Connects to the first address `authority` resolves to that accepts a
+/// connection within `EXPORT_TIMEOUT`.
+fn connect(authority: &str) -> io::Result<TcpStream> {
+    let mut last_err = None;
+    for addr in authority.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, EXPORT_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{authority} resolved to no addresses"),
+        )
+    }))
+}
+
+fn post(endpoint: &Endpoint, body: &[u8]) -> io::Result<()> {
+    let mut stream = connect(&endpoint.authority)?;
+    stream.set_read_timeout(Some(EXPORT_TIMEOUT))?;
+    stream.set_write_timeout(Some(EXPORT_TIMEOUT))?;
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        endpoint.path,
+        endpoint.authority,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+
+    let mut status_line = String::new();
+    BufReader::new(stream).read_line(&mut status_line)?;
+    match status_line.split_whitespace().nth(1) {
+        Some(status) if status.starts_with('2') => Ok(()),
+        _ => Err(io::Error::other(format!(
+            "unexpected response: {}",
+            status_line.trim()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_request() {
+        let records = [
+            OtlpRecord::new(&Record {
+                scope: ["zlog", "otlp", "", ""],
+                level: log::Level::Warn,
+                message: &format_args!("disk {}", "full"),
+                module_path: None,
+                line: None,
+                seq: 1,
+            }),
+            OtlpRecord::new(&Record {
+                scope: ["zlog", "otlp", "", ""],
+                level: log::Level::Debug,
+                message: &format_args!("retrying"),
+                module_path: None,
+                line: None,
+                seq: 2,
+            }),
+            OtlpRecord::new(&Record {
+                scope: ["", "", "", ""],
+                level: log::Level::Error,
+                message: &format_args!("failed"),
+                module_path: Some("project::worktree"),
+                line: None,
+                seq: 3,
+            }),
+        ];
+        let time_unix_nano = |index: usize| records[index].time_unix_nano.to_string();
+
+        assert_eq!(
+            export_request(&records, None),
+            json!({
+                "resourceLogs": [{
+                    "resource": {
+                        "attributes": [{
+                            "key": "service.name",
+                            "value": { "stringValue": process_name() },
+                        }],
+                    },
+                    "scopeLogs": [
+                        {
+                            "scope": {
+                                "name": "zlog.otlp",
+                                "attributes": [{
+                                    "key": "zlog.scope",
+                                    "value": {
+                                        "arrayValue": {
+                                            "values": [
+                                                { "stringValue": "zlog" },
+                                                { "stringValue": "otlp" },
+                                            ],
+                                        },
+                                    },
+                                }],
+                            },
+                            "logRecords": [
+                                {
+                                    "timeUnixNano": time_unix_nano(0),
+                                    "severityNumber": 13,
+                                    "severityText": "WARN",
+                                    "body": { "stringValue": "disk full" },
+                                    "attributes": [{
+                                        "key": "zlog.seq",
+                                        "value": { "intValue": "1" },
+                                    }],
+                                },
+                                {
+                                    "timeUnixNano": time_unix_nano(1),
+                                    "severityNumber": 5,
+                                    "severityText": "DEBUG",
+                                    "body": { "stringValue": "retrying" },
+                                    "attributes": [{
+                                        "key": "zlog.seq",
+                                        "value": { "intValue": "2" },
+                                    }],
+                                },
+                            ],
+                        },
+                        {
+                            "scope": {
+                                "name": "project::worktree",
+                                "attributes": [{
+                                    "key": "zlog.scope",
+                                    "value": {
+                                        "arrayValue": {
+                                            "values": [{ "stringValue": "project" }],
+                                        },
+                                    },
+                                }],
+                            },
+                            "logRecords": [{
+                                "timeUnixNano": time_unix_nano(2),
+                                "severityNumber": 17,
+                                "severityText": "ERROR",
+                                "body": { "stringValue": "failed" },
+                                "attributes": [{
+                                    "key": "zlog.seq",
+                                    "value": { "intValue": "3" },
+                                }],
+                            }],
+                        },
+                    ],
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn test_export_request_instance_tag() {
+        let records = [OtlpRecord::new(&Record {
+            scope: ["zlog", "", "", ""],
+            level: log::Level::Info,
+            message: &format_args!("tagged"),
+            module_path: None,
+            line: None,
+            seq: 0,
+        })];
+
+        let request = export_request(&records, Some("pid-42"));
+        assert_eq!(
+            request["resourceLogs"][0]["resource"]["attributes"][1],
+            json!({
+                "key": "service.instance.id",
+                "value": { "stringValue": "pid-42" },
+            })
+        );
+        let request = export_request(&records, None);
+        assert_eq!(
+            request["resourceLogs"][0]["resource"]["attributes"]
+                .as_array()
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_flush_waits_for_export() {
+        use log::Log as _;
+        use std::{io::Read as _, net::TcpListener, sync::Mutex};
+
+        let _lock = crate::filter::REFRESH_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let exported = Arc::new(Mutex::new(String::new()));
+        std::thread::spawn({
+            let exported = exported.clone();
+            move || {
+                for stream in listener.incoming().flatten() {
+                    let mut reader = BufReader::new(stream);
+                    let mut content_length = 0;
+                    let mut header = String::new();
+                    while reader.read_line(&mut header).unwrap() > 2 {
+                        if let Some(length) = header.to_lowercase().strip_prefix("content-length:")
+                        {
+                            content_length = length.trim().parse().unwrap();
+                        }
+                        header.clear();
+                    }
+                    let mut body = vec![0; content_length];
+                    reader.read_exact(&mut body).unwrap();
+                    exported
+                        .lock()
+                        .unwrap()
+                        .push_str(&String::from_utf8(body).unwrap());
+                    _ = reader
+                        .get_mut()
+                        .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+                }
+            }
+        });
+        init_output_otlp(&endpoint, OverflowPolicy::Block).unwrap();
+
+        let logger = crate::Logger {
+            scope: ["otlp_flush_test", "", "", ""],
+            fields: &[],
+        };
+        for ix in 0..100 {
+            logger.log(
+                &log::Record::builder()
+                    .level(log::Level::Info)
+                    .args(format_args!("flushed record {ix}"))
+                    .build(),
+            );
+        }
+        log::Log::flush(&logger);
+
+        let exported = exported.lock().unwrap();
+        for ix in 0..100 {
+            assert!(
+                exported.contains(&format!("\"flushed record {ix}\"")),
+                "record {ix} was not exported"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_endpoint() {
+        let endpoint = parse_endpoint("http://localhost:4318").unwrap();
+        assert_eq!(endpoint.authority, "localhost:4318");
+        assert_eq!(endpoint.path, "/v1/logs");
+
+        let endpoint = parse_endpoint("http://collector/custom/logs").unwrap();
+        assert_eq!(endpoint.authority, "collector:80");
+        assert_eq!(endpoint.path, "/custom/logs");
+
+        assert!(parse_endpoint("https://collector").is_err());
+        assert!(parse_endpoint("http://").is_err());
+    }
+
+    #[test]
+    fn test_failure_reports_are_rate_limited() {
+        let mut failures = FailureReports::default();
+        let start = Instant::now();
+        assert_eq!(failures.report(start), Some(0));
+        for seconds in 1..=3 {
+            assert_eq!(failures.report(start + Duration::from_secs(seconds)), None);
+        }
+        assert_eq!(
+            failures.report(start + FAILURE_REPORT_INTERVAL + Duration::from_secs(1)),
+            Some(3)
+        );
+        assert_eq!(
+            failures.report(start + FAILURE_REPORT_INTERVAL + Duration::from_secs(2)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_connect_fails_without_listener() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let authority = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        assert!(connect(&authority).is_err());
+    }
+}
@@ -0,0 +1,239 @@
+//! A bounded queue for handing records to a background export thread.
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        Condvar, Mutex, MutexGuard,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+/// What a background sink does with a record when its queue is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Discard the record being submitted.
+    #[default]
+    DropNewest,
+    /// Discard the oldest queued record to make room.
+    DropOldest,
+    /// Block the submitting thread until there is room.
+    Block,
+}
+
+pub(super) struct Queue<T> {
+    state: Mutex<State<T>>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    finished: Condvar,
+    capacity: usize,
+    policy: OverflowPolicy,
+    dropped: AtomicU64,
+}
+
+struct State<T> {
+    items: VecDeque<T>,
+    /// Items that were pushed but not yet reported as handled with `finish`.
+    unfinished: usize,
+    /// Set while a `flush` waits, so that the consumer handles what it has
+    /// taken instead of waiting for more.
+    flush_requested: bool,
+}
+
+impl<T> Queue<T> {
+    pub fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+        assert!(capacity > 0, "queue capacity must be non-zero");
+        Self {
+            state: Mutex::new(State {
+                items: VecDeque::with_capacity(capacity),
+                unfinished: 0,
+                flush_requested: false,
+            }),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            finished: Condvar::new(),
+            capacity,
+            policy,
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap_or_else(|handle| {
+            self.state.clear_poison();
+            handle.into_inner()
+        })
+    }
+
+    pub fn push(&self, item: T) {
+        let mut state = self.lock();
+        if state.items.len() >= self.capacity {
+            match self.policy {
+                OverflowPolicy::DropNewest => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+                OverflowPolicy::DropOldest => {
+                    state.items.pop_front();
+                    state.unfinished -= 1;
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                OverflowPolicy::Block => {
+                    state = self
+                        .not_full
+                        .wait_while(state, |state| state.items.len() >= self.capacity)
+                        .unwrap_or_else(|handle| handle.into_inner());
+                }
+            }
+        }
+        state.items.push_back(item);
+        state.unfinished += 1;
+        self.not_empty.notify_one();
+    }
+
+    /// Takes the oldest queued item, waiting up to `timeout` for one to be
+    /// pushed if the queue is empty. Returns `None` early if the queue is
+    /// empty and a `flush` is waiting.
+    pub fn pop_timeout(&self, timeout: Duration) -> Option<T> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.lock();
+        loop {
+            if let Some(item) = state.items.pop_front() {
+                self.not_full.notify_one();
+                return Some(item);
+            }
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            if timeout.is_zero() || state.flush_requested {
+                return None;
+            }
+            state = self
+                .not_empty
+                .wait_timeout(state, timeout)
+                .unwrap_or_else(|handle| handle.into_inner())
+                .0;
+        }
+    }
+
+    /// Reports that `count` items taken with `pop_timeout` have been handled.
+    pub fn finish(&self, count: usize) {
+        let mut state = self.lock();
+        state.unfinished -= count;
+        if state.unfinished == 0 {
+            state.flush_requested = false;
+            self.finished.notify_all();
+        }
+    }
+
+    /// Waits up to `timeout` for every pushed item to be handled, returning
+    /// whether they were.
+    pub fn flush(&self, timeout: Duration) -> bool {
+        let mut state = self.lock();
+        if state.unfinished == 0 {
+            return true;
+        }
+        state.flush_requested = true;
+        self.not_empty.notify_all();
+        let state = self
+            .finished
+            .wait_timeout_while(state, timeout, |state| state.unfinished > 0)
+            .unwrap_or_else(|handle| handle.into_inner())
+            .0;
+        state.unfinished == 0
+    }
+
+    /// The number of items discarded because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread};
+
+    use super::*;
+
+    fn drain(queue: &Queue<u32>) -> Vec<u32> {
+        std::iter::from_fn(|| queue.pop_timeout(Duration::ZERO)).collect()
+    }
+
+    #[test]
+    fn test_drop_newest() {
+        let queue = Queue::new(2, OverflowPolicy::DropNewest);
+        for item in 0..5 {
+            queue.push(item);
+        }
+        assert_eq!(drain(&queue), [0, 1]);
+        assert_eq!(queue.dropped(), 3);
+    }
+
+    #[test]
+    fn test_drop_oldest() {
+        let queue = Queue::new(2, OverflowPolicy::DropOldest);
+        for item in 0..5 {
+            queue.push(item);
+        }
+        assert_eq!(drain(&queue), [3, 4]);
+        assert_eq!(queue.dropped(), 3);
+    }
+
+    #[test]
+    fn test_block() {
+        let queue = Arc::new(Queue::new(2, OverflowPolicy::Block));
+        let producer = thread::spawn({
+            let queue = queue.clone();
+            move || {
+                for item in 0..5 {
+                    queue.push(item);
+                }
+            }
+        });
+
+        let mut received = Vec::new();
+        while received.len() < 5 {
+            thread::sleep(Duration::from_millis(5));
+            received.extend(queue.pop_timeout(Duration::from_secs(5)));
+        }
+        producer.join().unwrap();
+        assert_eq!(received, [0, 1, 2, 3, 4]);
+        assert_eq!(queue.dropped(), 0);
+    }
+
+    #[test]
+    fn test_flush_waits_for_finish() {
+        let queue = Arc::new(Queue::new(4, OverflowPolicy::default()));
+        assert!(queue.flush(Duration::ZERO));
+        queue.push(1);
+        queue.push(2);
+        assert!(!queue.flush(Duration::from_millis(1)));
+
+        let consumer = thread::spawn({
+            let queue = queue.clone();
+            move || {
+                let mut batch = Vec::new();
+                // Waits far longer than the test, unless a flush is requested.
+                while let Some(item) = queue.pop_timeout(Duration::from_secs(60)) {
+                    batch.push(item);
+                }
+                queue.finish(batch.len());
+                batch
+            }
+        });
+        assert!(queue.flush(Duration::from_secs(5)));
+        assert_eq!(consumer.join().unwrap(), [1, 2]);
+    }
+
+    #[test]
+    fn test_pop_timeout_waits_for_push() {
+        let queue = Arc::new(Queue::new(1, OverflowPolicy::default()));
+        assert_eq!(queue.pop_timeout(Duration::from_millis(1)), None);
+        let producer = thread::spawn({
+            let queue = queue.clone();
+            move || {
+                thread::sleep(Duration::from_millis(10));
+                queue.push(7);
+            }
+        });
+        assert_eq!(queue.pop_timeout(Duration::from_secs(5)), Some(7));
+        producer.join().unwrap();
+    }
+}
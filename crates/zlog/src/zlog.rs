@@ -1,14 +1,45 @@
 //! # logger
+//!
+//! Without the default `std` feature, only the scope and level filter logic in
+//! `filter_core` is built, for use in `no_std` environments with `alloc`.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 pub use log as log_impl;
 
+use alloc::string::{String, ToString};
+#[cfg(feature = "std")]
+use collections::{FxBuildHasher, HashSet};
+#[cfg(feature = "std")]
+use std::path::PathBuf;
+#[cfg(feature = "std")]
+use std::sync::{
+    Mutex, RwLock,
+    atomic::{AtomicBool, Ordering},
+};
+
+#[cfg(feature = "std")]
+pub mod context;
 mod env_config;
+#[cfg(feature = "std")]
 pub mod filter;
+pub mod filter_core;
+#[cfg(feature = "std")]
 pub mod sink;
 
-pub use sink::{flush, init_output_file, init_output_stderr, init_output_stdout};
+#[cfg(feature = "std")]
+pub use filter::{namespace, with_level};
+#[cfg(feature = "std")]
+pub use sink::{
+    EscapedBytes, OpenMode, ProcessMeta, flush, init_output_fallback, init_output_file,
+    init_output_json_with_meta, init_output_split, init_output_stderr, init_output_stdout,
+    init_output_writer, log_banner, try_flush,
+};
 
 pub const SCOPE_DEPTH_MAX: usize = 4;
 
+#[cfg(feature = "std")]
 pub fn init() {
     if let Err(err) = try_init(None) {
         log::error!("{err}");
@@ -16,35 +47,166 @@ pub fn init() {
     }
 }
 
+/// Whether `try_init` has installed `ZLOG` as the `log` logger.
+#[cfg(feature = "std")]
+static ZLOG_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Installs zlog as the `log` logger and applies the environment. Calling this
+/// again, e.g. from tests or after a plugin reload, succeeds and re-reads the
+/// filter from the environment, but fails if a different logger is installed.
+#[cfg(feature = "std")]
 pub fn try_init(filter: Option<String>) -> anyhow::Result<()> {
-    log::set_logger(&ZLOG)?;
-    log::set_max_level(log::LevelFilter::max());
+    if !ZLOG_INSTALLED.swap(true, Ordering::AcqRel) {
+        if let Err(err) = log::set_logger(&ZLOG) {
+            ZLOG_INSTALLED.store(false, Ordering::Release);
+            return Err(err.into());
+        }
+        log::set_max_level(log::LevelFilter::max());
+    }
     process_env(filter);
     filter::refresh_from_settings(&std::collections::HashMap::default());
     Ok(())
 }
 
+#[cfg(feature = "std")]
 pub fn init_test() {
     if get_env_config().is_some() && try_init(None).is_ok() {
         init_output_stdout();
     }
 }
 
+/// The environment variables holding the filter, in the order they are
+/// consulted. `None` means `DEFAULT_ENV_VAR_NAMES`.
+#[cfg(feature = "std")]
+static ENV_VAR_NAMES: RwLock<Option<Vec<String>>> = RwLock::new(None);
+
+#[cfg(feature = "std")]
+pub const DEFAULT_ENV_VAR_NAMES: &[&str] = &["ZED_LOG", "RUST_LOG"];
+
+/// Overrides the environment variables the filter is read from, in the order
+/// they are consulted, so that embedders can use their own. Call this before
+/// `init` for it to take effect.
+#[cfg(feature = "std")]
+pub fn set_env_var_names(names: &[&str]) {
+    *ENV_VAR_NAMES.write().unwrap_or_else(|err| {
+        ENV_VAR_NAMES.clear_poison();
+        err.into_inner()
+    }) = Some(names.iter().map(|name| name.to_string()).collect());
+}
+
+#[cfg(feature = "std")]
 fn get_env_config() -> Option<String> {
-    std::env::var("ZED_LOG")
-        .or_else(|_| std::env::var("RUST_LOG"))
-        .ok()
-        .or_else(|| {
-            if std::env::var("CI").is_ok() {
-                Some("info".to_owned())
-            } else {
-                None
+    let names = ENV_VAR_NAMES.read().unwrap_or_else(|err| {
+        ENV_VAR_NAMES.clear_poison();
+        err.into_inner()
+    });
+    let value = match names.as_deref() {
+        Some(names) => names.iter().find_map(|name| std::env::var(name).ok()),
+        None => DEFAULT_ENV_VAR_NAMES
+            .iter()
+            .find_map(|name| std::env::var(name).ok()),
+    };
+    drop(names);
+    value.or_else(|| {
+        if std::env::var("CI").is_ok() {
+            Some("info".to_owned())
+        } else {
+            None
+        }
+    })
+}
+
+/// Reads the filter from the file named by `@<path>`, as an alternative to
+/// long `ZED_LOG` values. Other filters are returned as is. A file that can't
+/// be read is reported and leaves the filter unset.
+#[cfg(feature = "std")]
+fn read_filter_file(filter: String) -> Option<String> {
+    let Some(path) = filter.trim().strip_prefix('@') else {
+        return Some(filter);
+    };
+    match std::fs::read_to_string(path) {
+        Ok(contents) => Some(env_config::filter_file_directives(&contents)),
+        Err(err) => {
+            eprintln!("Could not read log filter file {path}: {err}. Ignoring it");
+            None
+        }
+    }
+}
+
+/// Whether `ZED_LOG_OUTPUT` selected the log output, in which case callers
+/// should not initialize an output themselves.
+#[cfg(feature = "std")]
+static OUTPUT_FROM_ENV: AtomicBool = AtomicBool::new(false);
+
+#[cfg(feature = "std")]
+pub fn has_output_from_env() -> bool {
+    OUTPUT_FROM_ENV.load(Ordering::Acquire)
+}
+
+#[cfg(feature = "std")]
+fn init_output_from_env(output: &str, init_output: impl FnOnce(env_config::OutputTarget)) -> bool {
+    let Some(target) = env_config::parse_output(output) else {
+        eprintln!(
+            "Invalid ZED_LOG_OUTPUT \"{output}\", expected stdout, stderr, or file:<path>. Using the default output"
+        );
+        return false;
+    };
+    init_output(target);
+    true
+}
+
+#[cfg(feature = "std")]
+fn init_output(target: env_config::OutputTarget) {
+    match target {
+        env_config::OutputTarget::Stdout => init_output_stdout(),
+        env_config::OutputTarget::Stderr => init_output_stderr(),
+        env_config::OutputTarget::File(path) => {
+            let mut path_rotate = path.clone().into_os_string();
+            path_rotate.push(".old");
+            let path: &'static PathBuf = Box::leak(Box::new(path));
+            let path_rotate: &'static PathBuf = Box::leak(Box::new(path_rotate.into()));
+            if let Err(err) = init_output_file(path, Some(path_rotate), OpenMode::Append) {
+                eprintln!(
+                    "Could not open log file {}: {}... Defaulting to stderr",
+                    path.display(),
+                    err
+                );
+                init_output_stderr();
             }
-        })
+        }
+    }
 }
 
+/// Applies `ZED_LOG`, or `filter` if it isn't set, and `ZED_LOG_OUTPUT`.
+/// `ZED_LOG=@<path>` reads the filter from a file. The output can only be
+/// selected once, so `ZED_LOG_OUTPUT` is ignored if it already selected the
+/// output, or if `sink::configure` did.
+///
+/// `ZED_LOG=off` or `ZED_LOG_DISABLE=1` disables all logging, including the
+/// records that are enabled by default, with the least possible overhead.
+#[cfg(feature = "std")]
 pub fn process_env(filter: Option<String>) {
-    let Some(env_config) = get_env_config().or(filter) else {
+    if !has_output_from_env()
+        && !sink::has_configured_target()
+        && let Ok(output) = std::env::var("ZED_LOG_OUTPUT")
+        && init_output_from_env(&output, init_output)
+    {
+        OUTPUT_FROM_ENV.store(true, Ordering::Release);
+    }
+    let env_config = get_env_config().or(filter).and_then(read_filter_file);
+    let disabled = std::env::var("ZED_LOG_DISABLE").is_ok_and(|value| value == "1")
+        || env_config
+            .as_deref()
+            .is_some_and(|env_config| env_config.trim().eq_ignore_ascii_case("off"));
+    filter::set_disabled(disabled);
+    if ZLOG_INSTALLED.load(Ordering::Acquire) {
+        log::set_max_level(if disabled {
+            log::LevelFilter::Off
+        } else {
+            log::LevelFilter::max()
+        });
+    }
+    let Some(env_config) = env_config else {
         return;
     };
     match env_config::parse(&env_config) {
@@ -52,15 +214,68 @@ pub fn process_env(filter: Option<String>) {
             filter::init_env_filter(filter);
         }
         Err(err) => {
-            eprintln!("Failed to parse log filter: {}", err);
+            report_filter_parse_error(&err.to_string());
         }
     }
 }
 
+/// Hashes of the filter parse errors that have already been reported, so that
+/// re-applying the same invalid filter doesn't repeat them. Only the most recent
+/// `REPORTED_FILTER_ERRORS_MAX` are kept.
+#[cfg(feature = "std")]
+static REPORTED_FILTER_ERRORS: Mutex<std::collections::VecDeque<u64>> =
+    Mutex::new(std::collections::VecDeque::new());
+#[cfg(feature = "std")]
+const REPORTED_FILTER_ERRORS_MAX: usize = 32;
+
+/// Reports `err` on stderr, since the output may not be configured yet, and
+/// through the logger, unless the same error was already reported. Returns
+/// whether it was reported.
+#[cfg(feature = "std")]
+fn report_filter_parse_error(err: &str) -> bool {
+    {
+        let mut reported = REPORTED_FILTER_ERRORS.lock().unwrap_or_else(|handle| {
+            REPORTED_FILTER_ERRORS.clear_poison();
+            handle.into_inner()
+        });
+        let hash = std::hash::BuildHasher::hash_one(&FxBuildHasher, err);
+        if reported.contains(&hash) {
+            return false;
+        }
+        if reported.len() == REPORTED_FILTER_ERRORS_MAX {
+            reported.pop_front();
+        }
+        reported.push_back(hash);
+    }
+    eprintln!("Failed to parse log filter: {}", err);
+    crate::error!(crate::scoped!("filter") => "Failed to parse log filter: {}", err);
+    true
+}
+
+#[cfg(feature = "std")]
 static ZLOG: Zlog = Zlog {};
 
+#[cfg(feature = "std")]
 pub struct Zlog {}
 
+/// Whether a record logged through the `log` facade from `module_path` at
+/// `level` would be emitted. The `log` macros format their arguments before the
+/// logger gets to filter the record, so hot code can check this first to skip
+/// that work.
+#[cfg(feature = "std")]
+pub fn would_log(level: log::Level, module_path: &str) -> bool {
+    if !filter::is_possibly_enabled_level(level) {
+        return false;
+    }
+    let crate_name = private::extract_crate_name_from_module_path(module_path);
+    filter::is_scope_enabled(
+        &private::scope_ref_new(&[crate_name]),
+        Some(module_path),
+        level,
+    )
+}
+
+#[cfg(feature = "std")]
 impl log::Log for Zlog {
     fn enabled(&self, metadata: &log::Metadata) -> bool {
         filter::is_possibly_enabled_level(metadata.level())
@@ -70,7 +285,14 @@ impl log::Log for Zlog {
         if !self.enabled(record.metadata()) {
             return;
         }
-        let module_path = record.module_path().or(record.file());
+        // Records without a module path use their file path in its place. It is
+        // interned like a module path, and since it has no `::`, the whole file
+        // path also becomes the record's crate name scope.
+        let module_path = record_module_path(record).or_else(|| {
+            record
+                .file_static()
+                .or_else(|| record.file().map(intern_module_path))
+        });
         let (crate_name_scope, module_scope) = match module_path {
             Some(module_path) => {
                 let crate_name = private::extract_crate_name_from_module_path(module_path);
@@ -79,21 +301,25 @@ impl log::Log for Zlog {
                 (crate_name_scope, module_scope)
             }
             None => {
-                // TODO: when do we hit this
-                (private::scope_new(&[]), private::scope_new(&["*unknown*"]))
+                // Records from code compiled without module paths, or logged
+                // through the `log` facade from FFI
+                let unknown_scope = private::scope_new(&[SCOPE_UNKNOWN]);
+                (unknown_scope, unknown_scope)
             }
         };
         let level = record.metadata().level();
-        if !filter::is_scope_enabled(&crate_name_scope, Some(record.target()), level) {
+        if !filter::is_scope_enabled(&crate_name_scope, Some(record.target()), level)
+            || !filter::is_sampled(&crate_name_scope, Some(record.target()))
+        {
             return;
         }
         sink::submit(sink::Record {
             scope: module_scope,
             level,
             message: record.args(),
-            // PERF(batching): store non-static paths in a cache + leak them and pass static str here
             module_path,
             line: record.line(),
+            seq: 0,
         });
     }
 
@@ -102,20 +328,62 @@ impl log::Log for Zlog {
     }
 }
 
+/// Module paths that were not `'static` when logged, leaked exactly once so
+/// that records can always carry a `&'static str` module path. This includes
+/// the file paths of records logged without a module path.
+#[cfg(feature = "std")]
+static MODULE_PATH_CACHE: RwLock<HashSet<&'static str>> =
+    RwLock::new(HashSet::with_hasher(FxBuildHasher));
+
+#[cfg(feature = "std")]
+fn intern_module_path(module_path: &str) -> &'static str {
+    {
+        let cache = MODULE_PATH_CACHE.read().unwrap_or_else(|err| {
+            MODULE_PATH_CACHE.clear_poison();
+            err.into_inner()
+        });
+        if let Some(interned) = cache.get(module_path) {
+            return interned;
+        }
+    }
+    let mut cache = MODULE_PATH_CACHE.write().unwrap_or_else(|err| {
+        MODULE_PATH_CACHE.clear_poison();
+        err.into_inner()
+    });
+    // another thread may have interned it between releasing the read lock and acquiring the write lock
+    if let Some(interned) = cache.get(module_path) {
+        return interned;
+    }
+    let interned: &'static str = Box::leak(module_path.to_owned().into_boxed_str());
+    cache.insert(interned);
+    interned
+}
+
+#[cfg(feature = "std")]
+fn record_module_path(record: &log::Record) -> Option<&'static str> {
+    record
+        .module_path_static()
+        .or_else(|| record.module_path().map(intern_module_path))
+}
+
 #[macro_export]
 macro_rules! log {
     ($logger:expr, $level:expr, $($arg:tt)+) => {
         let level = $level;
-        let logger = $logger;
-        let enabled = $crate::filter::is_scope_enabled(&logger.scope, Some(module_path!()), level);
-        if enabled {
-            $crate::sink::submit($crate::sink::Record {
-                scope: logger.scope,
-                level,
-                message: &format_args!($($arg)+),
-                module_path: Some(module_path!()),
-                line: Some(line!()),
-            });
+        if level <= $crate::filter::LEVEL_ENABLED_MAX_COMPILE_TIME {
+            let logger = $logger;
+            let enabled = $crate::filter::is_scope_enabled(&logger.scope, Some(module_path!()), level)
+                && $crate::filter::is_sampled(&logger.scope, Some(module_path!()));
+            if enabled {
+                $crate::private::submit(logger, $crate::sink::Record {
+                    scope: logger.scope,
+                    level,
+                    message: &format_args!($($arg)+),
+                    module_path: Some(module_path!()),
+                    line: Some(line!()),
+                    seq: 0,
+                });
+            }
         }
     }
 }
@@ -170,6 +438,46 @@ macro_rules! error {
     };
 }
 
+/// Logs `expr = value` at the `debug` level, formatting the value with `Debug`,
+/// and returns it. Unlike `std::dbg!`, the record is subject to the scope and
+/// level filter rather than always written to stderr.
+#[macro_export]
+macro_rules! log_dbg {
+    ($logger:expr => $value:expr $(,)?) => {
+        match $value {
+            value => {
+                $crate::debug!($logger => "{} = {:?}", stringify!($value), &value);
+                value
+            }
+        }
+    };
+    ($value:expr $(,)?) => {
+        $crate::log_dbg!($crate::default_logger!() => $value)
+    };
+}
+
+/// Runs a closure, catching any panic and logging it at the `error` level.
+/// Returns `Some` with the closure's result, or `None` if it panicked.
+#[macro_export]
+macro_rules! catch_and_log {
+    ($logger:expr => $f:expr) => {
+        match ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe($f)) {
+            Ok(value) => Some(value),
+            Err(payload) => {
+                $crate::error!(
+                    $logger =>
+                    "Caught panic: {}",
+                    $crate::private::panic_message(&*payload)
+                );
+                None
+            }
+        }
+    };
+    ($f:expr) => {
+        $crate::catch_and_log!($crate::default_logger!() => $f)
+    };
+}
+
 /// Creates a timer that logs the duration it was active for either when
 /// it is dropped, or when explicitly stopped using the `end` method.
 /// Logs at the `trace` level.
@@ -190,13 +498,42 @@ macro_rules! time {
     };
 }
 
+/// Enters a child scope of the given logger until the returned `Span` is
+/// dropped, and logs how long it was entered for like `time!`. Bare logging
+/// macros inside the span log to the child scope, which is also available as
+/// `span.logger`.
+#[macro_export]
+macro_rules! span {
+    ($logger:expr => $name:expr) => {
+        $crate::Span::new(
+            $crate::private::scoped_logger_checked($logger, $name),
+            $name,
+        )
+    };
+    ($name:expr) => {
+        $crate::span!($crate::default_logger!() => $name)
+    };
+}
+
 #[macro_export]
 macro_rules! scoped {
-    ($parent:expr => $name:expr) => {{
-        $crate::scoped_logger($parent, $name)
+    // A parent that is itself `scoped!` has a statically known minimum depth,
+    // so nesting too deeply fails to compile instead of overflowing at runtime.
+    (scoped!($($parent:tt)+) => $name:expr) => {{
+        const _: () = assert!(
+            $crate::private::scope_depth_fits($crate::__scope_depth_min!(
+                scoped!($($parent)+) => $name
+            )),
+            "scoped! is nested deeper than SCOPE_DEPTH_MAX"
+        );
+        $crate::scoped_logger($crate::scoped!($($parent)+), $name)
     }};
+    ($parent:expr => $name:expr) => {
+        $crate::scoped_logger($parent, $name)
+    };
+    // Always const, as a single scope under the crate name can't overflow.
     ($name:expr) => {
-        $crate::scoped!($crate::default_logger!() => $name)
+        $crate::scoped_logger($crate::crate_logger!(), $name)
     };
 }
 
@@ -211,20 +548,92 @@ pub const fn scoped_logger(parent: Logger, name: &'static str) -> Logger {
         {
             panic!("Scope overflow trying to add scope... ignoring scope");
         }
+        #[cfg(not(debug_assertions))]
+        {
+            return parent;
+        }
     }
     scope[index] = name;
-    Logger { scope }
+    Logger {
+        scope,
+        fields: parent.fields,
+    }
+}
+
+#[cfg(feature = "std")]
+thread_local! {
+    static CURRENT_DEFAULT_LOGGER: std::cell::Cell<Option<Logger>> = const { std::cell::Cell::new(None) };
+}
+
+/// Runs `f` with `logger` as the default logger for this thread, so that bare
+/// logging macros in `f`, including in helpers it calls, log to its scope.
+#[cfg(feature = "std")]
+pub fn with_scope<R>(logger: Logger, f: impl FnOnce() -> R) -> R {
+    let _guard = enter_scope(logger);
+    f()
 }
 
+/// Makes `logger` the default logger for this thread until the returned guard
+/// is dropped, at which point the previous default is restored.
+#[cfg(feature = "std")]
+#[must_use]
+pub fn enter_scope(logger: Logger) -> DefaultLoggerGuard {
+    DefaultLoggerGuard {
+        previous: CURRENT_DEFAULT_LOGGER.with(|current| current.replace(Some(logger))),
+    }
+}
+
+#[cfg(feature = "std")]
+pub struct DefaultLoggerGuard {
+    previous: Option<Logger>,
+}
+
+#[cfg(feature = "std")]
+impl Drop for DefaultLoggerGuard {
+    fn drop(&mut self) {
+        _ = CURRENT_DEFAULT_LOGGER.try_with(|current| current.set(self.previous));
+    }
+}
+
+/// The logger used by the logging macros when none is given: the innermost
+/// logger set with `with_scope` or `enter_scope` on this thread, or else the
+/// calling crate's logger.
 #[macro_export]
 macro_rules! default_logger {
+    () => {
+        $crate::private::default_logger($crate::crate_logger!())
+    };
+}
+
+/// The logger scoped to the calling crate. Unlike `default_logger!`, this can
+/// be used in const contexts.
+#[macro_export]
+macro_rules! crate_logger {
     () => {
         $crate::Logger {
             scope: $crate::private::scope_new(&[$crate::crate_name!()]),
+            fields: &[],
         }
     };
 }
 
+/// The least depth of the scope created by `scoped!` with the given arguments,
+/// counting only what is known from nested `scoped!` invocations.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __scope_depth_min {
+    (scoped!($($parent:tt)+) => $name:expr) => {
+        1 + $crate::__scope_depth_min!($($parent)+)
+    };
+    // Every logger has at least the crate scope.
+    ($parent:expr => $name:expr) => {
+        2
+    };
+    ($name:expr) => {
+        2
+    };
+}
+
 #[macro_export]
 macro_rules! crate_name {
     () => {
@@ -237,6 +646,46 @@ macro_rules! crate_name {
 pub mod private {
     use super::*;
 
+    /// Like `scoped_logger`, but in release builds logs a warning the first time
+    /// a scope is dropped because `parent` is already `SCOPE_DEPTH_MAX` deep.
+    /// Only macros that never run in const contexts, like `span!`, use it, so
+    /// that `scoped!` stays const.
+    #[cfg(feature = "std")]
+    pub fn scoped_logger_checked(parent: Logger, name: &'static str) -> Logger {
+        static OVERFLOW_WARNED: AtomicBool = AtomicBool::new(false);
+        if cfg!(not(debug_assertions))
+            && !parent.scope[SCOPE_DEPTH_MAX - 1].is_empty()
+            && !OVERFLOW_WARNED.swap(true, Ordering::Relaxed)
+        {
+            crate::warn!(
+                crate::crate_logger!() =>
+                "Scope overflow: dropped {name:?} from scope {:?}, max depth is {SCOPE_DEPTH_MAX}",
+                parent.scope.join(SCOPE_STRING_SEP_STR)
+            );
+        }
+        scoped_logger(parent, name)
+    }
+
+    /// Whether a scope `depth` scopes deep can be represented without overflow.
+    pub const fn scope_depth_fits(depth: usize) -> bool {
+        depth <= SCOPE_DEPTH_MAX
+    }
+
+    /// Submits `record` with the fields of the logger it is logged through.
+    #[cfg(feature = "std")]
+    pub fn submit(logger: Logger, record: sink::Record) {
+        context::with_logger_fields(logger.fields, || sink::submit(record));
+    }
+
+    #[cfg(feature = "std")]
+    pub fn default_logger(crate_logger: Logger) -> Logger {
+        CURRENT_DEFAULT_LOGGER
+            .try_with(std::cell::Cell::get)
+            .ok()
+            .flatten()
+            .unwrap_or(crate_logger)
+    }
+
     pub const fn extract_crate_name_from_module_path(module_path: &str) -> &str {
         let mut i = 0;
         let mod_path_bytes = module_path.as_bytes();
@@ -254,6 +703,17 @@ pub mod private {
         crate_name
     }
 
+    #[cfg(feature = "std")]
+    pub fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+        if let Some(message) = payload.downcast_ref::<&str>() {
+            message
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message
+        } else {
+            "panic payload is not a string"
+        }
+    }
+
     pub const fn scope_new(scopes: &[&'static str]) -> Scope {
         scope_ref_new(scopes)
     }
@@ -285,13 +745,57 @@ pub type Scope = [&'static str; SCOPE_DEPTH_MAX];
 pub type ScopeRef<'a> = [&'a str; SCOPE_DEPTH_MAX];
 pub type ScopeAlloc = [String; SCOPE_DEPTH_MAX];
 const SCOPE_STRING_SEP_STR: &str = ".";
+#[cfg(feature = "std")]
 const SCOPE_STRING_SEP_CHAR: char = '.';
+/// Scope of records that have neither a module path nor a file. It can be
+/// filtered like any other scope.
+pub const SCOPE_UNKNOWN: &str = "*unknown*";
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Logger {
     pub scope: Scope,
+    /// Key-value fields attached to every record logged through this logger,
+    /// see `with_fields`.
+    pub fields: &'static [(&'static str, &'static str)],
 }
 
+impl Logger {
+    /// Attaches `fields` to every record logged through the returned logger,
+    /// and through loggers scoped under it, replacing any fields of this
+    /// logger. Records also carry the thread's `context`, which takes
+    /// precedence for keys set in both.
+    pub const fn with_fields(self, fields: &'static [(&'static str, &'static str)]) -> Logger {
+        Logger {
+            scope: self.scope,
+            fields,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Logger {
+    /// Installs this logger as the global `log` logger, so that all records
+    /// logged through the `log` facade are attributed to its scope. This is an
+    /// alternative to `init` for helper binaries that always log under one
+    /// scope. Output still has to be enabled with one of the `init_output_*`
+    /// functions.
+    pub fn install_global(self) -> Result<(), log::SetLoggerError> {
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(log::LevelFilter::max());
+        Ok(())
+    }
+
+    /// Whether records logged through this logger at `level` would be emitted,
+    /// taking its scope into account, unlike `log::Log::enabled`. Lets callers
+    /// skip expensive work that only feeds a record.
+    pub fn is_enabled(&self, level: log::Level) -> bool {
+        level <= filter::LEVEL_ENABLED_MAX_COMPILE_TIME
+            && filter::is_possibly_enabled_level(level)
+            && filter::is_scope_enabled(&self.scope, None, level)
+    }
+}
+
+#[cfg(feature = "std")]
 impl log::Log for Logger {
     fn enabled(&self, metadata: &log::Metadata) -> bool {
         filter::is_possibly_enabled_level(metadata.level())
@@ -302,16 +806,22 @@ impl log::Log for Logger {
             return;
         }
         let level = record.metadata().level();
-        if !filter::is_scope_enabled(&self.scope, Some(record.target()), level) {
+        if !filter::is_scope_enabled(&self.scope, Some(record.target()), level)
+            || !filter::is_sampled(&self.scope, Some(record.target()))
+        {
             return;
         }
-        sink::submit(sink::Record {
-            scope: self.scope,
-            level,
-            message: record.args(),
-            module_path: record.module_path(),
-            line: record.line(),
-        });
+        private::submit(
+            *self,
+            sink::Record {
+                scope: self.scope,
+                level,
+                message: record.args(),
+                module_path: record_module_path(record),
+                line: record.line(),
+                seq: 0,
+            },
+        );
     }
 
     fn flush(&self) {
@@ -319,34 +829,74 @@ impl log::Log for Logger {
     }
 }
 
+#[cfg(feature = "std")]
 pub struct Timer {
     pub logger: Logger,
-    pub start_time: std::time::Instant,
-    pub name: &'static str,
+    /// Is `None` if the timer can't log, i.e. if neither `trace` nor, with
+    /// `warn_if_gt`, `warn` is enabled for its scope, and there is no
+    /// [`sink::set_duration_sink`] to report to, so that it skips reading the
+    /// clock.
+    pub start_time: Option<std::time::Instant>,
+    /// Borrowed for static names, so that timing them doesn't allocate.
+    pub name: std::borrow::Cow<'static, str>,
     pub warn_if_longer_than: Option<std::time::Duration>,
     pub done: bool,
 }
 
+#[cfg(feature = "std")]
+pub struct Span {
+    pub logger: Logger,
+    // Dropped in declaration order, so the timer is logged to the span's scope
+    // before the previous default logger is restored.
+    _timer: Timer,
+    _default_logger: DefaultLoggerGuard,
+}
+
+#[cfg(feature = "std")]
+impl Span {
+    #[must_use = "Span will end when dropped, the result of this function should be saved in a variable prefixed with `_` if it should end when dropped"]
+    pub fn new(logger: Logger, name: &'static str) -> Self {
+        Self {
+            logger,
+            _timer: Timer::new(logger, name),
+            _default_logger: enter_scope(logger),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
 impl Drop for Timer {
     fn drop(&mut self) {
         self.finish();
     }
 }
 
+#[cfg(feature = "std")]
 impl Timer {
     #[must_use = "Timer will stop when dropped, the result of this function should be saved in a variable prefixed with `_` if it should stop when dropped"]
-    pub fn new(logger: Logger, name: &'static str) -> Self {
+    pub fn new(logger: Logger, name: impl Into<std::borrow::Cow<'static, str>>) -> Self {
         Self {
             logger,
-            name,
-            start_time: std::time::Instant::now(),
+            name: name.into(),
+            start_time: Self::start_if_enabled(logger, log::Level::Trace),
             warn_if_longer_than: None,
             done: false,
         }
     }
 
+    fn start_if_enabled(logger: Logger, level: log::Level) -> Option<std::time::Instant> {
+        let enabled = sink::has_duration_sink()
+            || sink::log_timer_durations()
+                && level <= filter::LEVEL_ENABLED_MAX_COMPILE_TIME
+                && filter::is_scope_enabled(&logger.scope, None, level);
+        enabled.then(std::time::Instant::now)
+    }
+
     pub fn warn_if_gt(mut self, warn_limit: std::time::Duration) -> Self {
         self.warn_if_longer_than = Some(warn_limit);
+        if self.start_time.is_none() {
+            self.start_time = Self::start_if_enabled(self.logger, log::Level::Warn);
+        }
         self
     }
 
@@ -358,10 +908,27 @@ impl Timer {
         if self.done {
             return;
         }
-        let elapsed = self.start_time.elapsed();
+        self.done = true;
+        let Some(start_time) = self.start_time else {
+            return;
+        };
+        let elapsed = start_time.elapsed();
+        if sink::has_duration_sink() {
+            sink::report_duration(&self.name, elapsed);
+        }
+        if !sink::log_timer_durations() {
+            return;
+        }
         if let Some(warn_limit) = self.warn_if_longer_than
             && elapsed > warn_limit
         {
+            // Structured fields for alerting on slow operations, alongside the
+            // human readable message.
+            let _fields = [
+                context::scoped("op", self.name.clone()),
+                context::scoped("elapsed_ms", elapsed.as_millis().to_string()),
+                context::scoped("limit_ms", warn_limit.as_millis().to_string()),
+            ];
             crate::warn!(
                 self.logger =>
                 "Timer '{}' took {:?}. Which was longer than the expected limit of {:?}",
@@ -369,7 +936,6 @@ impl Timer {
                 elapsed,
                 warn_limit
             );
-            self.done = true;
             return;
         }
         crate::trace!(
@@ -378,7 +944,6 @@ impl Timer {
             self.name,
             elapsed
         );
-        self.done = true;
     }
 }
 
@@ -406,4 +971,510 @@ mod tests {
             "my_speedy_crate_"
         );
     }
+
+    #[test]
+    fn test_init_output_from_env() {
+        let init_from = |output: &str| {
+            let mut initialized = None;
+            let applied = init_output_from_env(output, |target| initialized = Some(target));
+            assert_eq!(applied, initialized.is_some());
+            initialized
+        };
+        assert_eq!(init_from("stdout"), Some(env_config::OutputTarget::Stdout));
+        assert_eq!(init_from("stderr"), Some(env_config::OutputTarget::Stderr));
+        assert_eq!(
+            init_from("file:/var/log/zed.log"),
+            Some(env_config::OutputTarget::File(PathBuf::from(
+                "/var/log/zed.log"
+            )))
+        );
+        assert_eq!(init_from("nowhere"), None);
+    }
+
+    #[test]
+    fn test_catch_and_log() {
+        assert_eq!(catch_and_log!(|| 1 + 1), Some(2));
+        assert_eq!(
+            catch_and_log!(scoped!("catch") => || -> i32 { panic!("oh no") }),
+            None
+        );
+
+        let payload = std::panic::catch_unwind(|| panic!("static message")).unwrap_err();
+        assert_eq!(private::panic_message(&*payload), "static message");
+        let payload = std::panic::catch_unwind(|| panic!("formatted {}", "message")).unwrap_err();
+        assert_eq!(private::panic_message(&*payload), "formatted message");
+        let payload = std::panic::catch_unwind(|| std::panic::panic_any(42)).unwrap_err();
+        assert_eq!(
+            private::panic_message(&*payload),
+            "panic payload is not a string"
+        );
+    }
+
+    #[test]
+    fn test_timer_skips_measurement_when_disabled() {
+        let _lock = filter::REFRESH_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        crate::sink::captured_output();
+        filter::refresh_from_settings(&collections::HashMap::from_iter([
+            ("zlog.timer_off".to_string(), "off".to_string()),
+            ("zlog.timer_warn".to_string(), "warn".to_string()),
+            ("zlog.timer_trace".to_string(), "trace".to_string()),
+        ]));
+
+        let disabled =
+            time!(scoped!("timer_off") => "disabled timer").warn_if_gt(std::time::Duration::ZERO);
+        assert!(disabled.start_time.is_none());
+        drop(disabled);
+
+        let trace_disabled = time!(scoped!("timer_warn") => "quick timer");
+        assert!(trace_disabled.start_time.is_none());
+        drop(trace_disabled);
+
+        let slow =
+            time!(scoped!("timer_warn") => "slow timer").warn_if_gt(std::time::Duration::ZERO);
+        assert!(slow.start_time.is_some());
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        drop(slow);
+
+        let traced = time!(scoped!("timer_trace") => "traced timer");
+        assert!(traced.start_time.is_some());
+        traced.end();
+        flush();
+        filter::refresh_from_settings(&collections::HashMap::default());
+
+        let output = crate::sink::captured_output();
+        assert!(!output.contains("disabled timer"), "{output}");
+        assert!(!output.contains("quick timer"), "{output}");
+        assert!(output.contains("Timer 'slow timer' took"), "{output}");
+        assert!(
+            output.contains("Timer 'traced timer' finished in"),
+            "{output}"
+        );
+    }
+
+    #[test]
+    fn test_duration_sink() {
+        let _lock = filter::REFRESH_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        crate::sink::captured_output();
+        filter::refresh_from_settings(&collections::HashMap::from_iter([(
+            "zlog.duration_sink".to_string(),
+            "off".to_string(),
+        )]));
+        let durations = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        sink::set_duration_sink(Box::new({
+            let durations = durations.clone();
+            move |name, duration| durations.lock().unwrap().push((name.to_string(), duration))
+        }));
+
+        let timer = time!(scoped!("duration_sink") => "muted duration timer");
+        assert!(timer.start_time.is_some());
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        timer.end();
+        sink::set_log_timer_durations(false);
+        time!(crate_logger!() => "unlogged duration timer").end();
+        sink::set_log_timer_durations(true);
+        sink::clear_duration_sink();
+        time!(crate_logger!() => "unreported duration timer").end();
+        flush();
+        filter::refresh_from_settings(&collections::HashMap::default());
+
+        let durations = durations.lock().unwrap();
+        let names = durations
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .filter(|name| name.ends_with("duration timer"))
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["muted duration timer", "unlogged duration timer"]);
+        let (_, duration) = &durations[0];
+        assert!(
+            *duration >= std::time::Duration::from_millis(2)
+                && *duration < std::time::Duration::from_secs(10),
+            "{duration:?}"
+        );
+        let output = crate::sink::captured_output();
+        assert!(!output.contains("muted duration timer"), "{output}");
+        assert!(!output.contains("unlogged duration timer"), "{output}");
+    }
+
+    #[test]
+    fn test_log_dbg() {
+        let _lock = filter::REFRESH_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        crate::sink::captured_output();
+        filter::refresh_from_settings(&collections::HashMap::from_iter([(
+            "zlog.dbg_test".to_string(),
+            "debug".to_string(),
+        )]));
+
+        let logger = scoped!("dbg_test");
+        let values = vec![1, 2];
+        let values = log_dbg!(logger => values);
+        assert_eq!(values, [1, 2]);
+        let sum = with_scope(logger, || log_dbg!(values[0] + values[1]));
+        assert_eq!(sum, 3);
+        flush();
+        filter::refresh_from_settings(&collections::HashMap::default());
+
+        let output = crate::sink::captured_output();
+        for suffix in ["values = [1, 2]", "values[0] + values[1] = 3"] {
+            let line = output
+                .lines()
+                .find(|line| line.ends_with(suffix))
+                .unwrap_or_else(|| panic!("no line ending with {suffix:?}"));
+            assert!(line.contains(" DEBUG [zlog.dbg_test] "), "{line}");
+        }
+    }
+
+    #[test]
+    fn test_logger_fields() {
+        let _lock = filter::REFRESH_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        crate::sink::captured_output();
+
+        let logger = scoped!("fields_test").with_fields(&[("component", "parser")]);
+        {
+            let _guard = context::scoped("file", "main.rs");
+            error!(logger => "parsed with fields");
+        }
+        error!(scoped!(logger => "nested") => "parsed in a nested scope");
+        error!(scoped!("fields_test") => "parsed without fields");
+        flush();
+
+        let output = crate::sink::captured_output();
+        let find_line = |needle: &str| {
+            output
+                .lines()
+                .find(|line| line.contains(needle))
+                .unwrap_or_else(|| panic!("no line containing {needle:?}"))
+        };
+        assert!(
+            find_line("parsed with fields")
+                .ends_with("parsed with fields component=parser file=main.rs")
+        );
+        assert!(find_line("parsed in a nested scope").ends_with(" component=parser"));
+        assert!(find_line("parsed without fields").ends_with("parsed without fields"));
+    }
+
+    #[test]
+    #[cfg(all(feature = "release_max_level_info", not(debug_assertions)))]
+    fn test_release_max_level_info_skips_debug_arguments() {
+        let _lock = filter::REFRESH_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        crate::filter::refresh_from_settings(&collections::HashMap::from_iter([(
+            "zlog".to_string(),
+            "trace".to_string(),
+        )]));
+        let mut evaluated = false;
+        debug!("{}", {
+            evaluated = true;
+            "side effect"
+        });
+        assert!(!evaluated);
+    }
+
+    #[test]
+    fn test_with_scope() {
+        let outer = scoped!("outer");
+        let inner = scoped!(outer => "inner");
+        assert_eq!(default_logger!(), crate_logger!());
+
+        with_scope(outer, || {
+            assert_eq!(default_logger!(), outer);
+            {
+                let _guard = enter_scope(inner);
+                assert_eq!(default_logger!().scope, ["zlog", "outer", "inner", ""]);
+            }
+            assert_eq!(default_logger!(), outer);
+            std::thread::spawn(|| assert_eq!(default_logger!(), crate_logger!()))
+                .join()
+                .unwrap();
+        });
+
+        assert_eq!(default_logger!(), crate_logger!());
+    }
+
+    #[test]
+    fn test_scoped_in_const() {
+        const PARENT: Logger = scoped!("const_parent");
+        const CHILD: Logger = scoped!(PARENT => "child");
+        const NESTED: Logger = scoped!(scoped!("const_parent") => "child");
+        assert_eq!(CHILD.scope, [crate_name!(), "const_parent", "child", ""]);
+        assert_eq!(NESTED, CHILD);
+    }
+
+    #[test]
+    fn test_nested_scoped_at_max_depth() {
+        let logger = scoped!(scoped!(scoped!("a") => "b") => "c");
+        assert_eq!(logger.scope, [crate_name!(), "a", "b", "c"]);
+        assert_eq!(__scope_depth_min!(scoped!(scoped!("a") => "b") => "c"), 4);
+        assert_eq!(__scope_depth_min!(logger => "d"), 2);
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))]
+    fn test_scope_overflow_warns_once() {
+        let _lock = filter::REFRESH_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        crate::sink::captured_output();
+        let full = scoped!(scoped!(scoped!("a") => "b") => "c");
+        assert_eq!(
+            private::scoped_logger_checked(full, "overflow_dropped"),
+            full
+        );
+        assert_eq!(
+            private::scoped_logger_checked(full, "overflow_dropped_again"),
+            full
+        );
+        flush();
+
+        let output = crate::sink::captured_output();
+        let warnings = output
+            .lines()
+            .filter(|line| line.contains("Scope overflow"))
+            .collect::<Vec<_>>();
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert!(warnings[0].contains("\"overflow_dropped\""));
+        assert!(warnings[0].contains(" WARN  [zlog"));
+    }
+
+    #[test]
+    fn test_unknown_module_path() {
+        use log::Log as _;
+
+        let _lock = filter::REFRESH_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        crate::sink::captured_output();
+        Zlog {}.log(
+            &log::Record::builder()
+                .args(format_args!("record without a module path"))
+                .level(log::Level::Error)
+                .target("ffi")
+                .module_path(None)
+                .file(None)
+                .build(),
+        );
+        flush();
+        let output = crate::sink::captured_output();
+        let line = output
+            .lines()
+            .find(|line| line.ends_with("record without a module path"))
+            .expect("record without a module path is emitted");
+        assert!(line.contains(" ERROR [*unknown*] "), "{line}");
+
+        let map = filter::ScopeMap::new_from_settings_and_env(
+            &collections::HashMap::from_iter([(SCOPE_UNKNOWN.to_string(), "off".to_string())]),
+            None,
+            &[],
+        );
+        assert_eq!(
+            map.is_enabled(
+                &private::scope_new(&[SCOPE_UNKNOWN]),
+                Some("ffi"),
+                log::Level::Error
+            ),
+            filter::EnabledStatus::Disabled
+        );
+    }
+
+    #[test]
+    fn test_span() {
+        let _lock = filter::REFRESH_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        crate::sink::captured_output();
+        filter::refresh_from_settings(&collections::HashMap::from_iter([(
+            "zlog.span_test".to_string(),
+            "trace".to_string(),
+        )]));
+
+        {
+            let span = span!(crate_logger!() => "span_test");
+            info!("logged inside the span");
+            info!(span.logger => "logged through the span");
+        }
+        info!("logged after the span");
+        flush();
+        filter::refresh_from_settings(&collections::HashMap::default());
+
+        let output = crate::sink::captured_output();
+        let find_line = |suffix: &str| {
+            output
+                .lines()
+                .find(|line| line.ends_with(suffix))
+                .unwrap_or_else(|| panic!("no line ending with {suffix:?}"))
+        };
+        assert!(find_line("logged inside the span").contains(" [zlog.span_test] "));
+        assert!(find_line("logged through the span").contains(" [zlog.span_test] "));
+        assert!(!find_line("logged after the span").contains("span_test"));
+        if filter::LEVEL_ENABLED_MAX_COMPILE_TIME == log::LevelFilter::Trace {
+            assert!(output.lines().any(|line| {
+                line.contains(" TRACE [zlog.span_test] Timer 'span_test' finished in")
+            }));
+        }
+    }
+
+    #[test]
+    fn test_timer_dynamic_name() {
+        let _lock = filter::REFRESH_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        crate::sink::captured_output();
+        let path = "src/dynamic_timer.rs";
+        let timer =
+            time!(crate_logger!() => format!("load {path}")).warn_if_gt(std::time::Duration::ZERO);
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        timer.end();
+        assert!(matches!(
+            Timer::new(crate_logger!(), "static").name,
+            std::borrow::Cow::Borrowed("static")
+        ));
+        flush();
+
+        let output = crate::sink::captured_output();
+        assert!(
+            output
+                .lines()
+                .any(|line| line.contains("Timer 'load src/dynamic_timer.rs' took")),
+            "{output}"
+        );
+    }
+
+    #[test]
+    fn test_timer_over_limit_fields() {
+        let _lock = filter::REFRESH_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        crate::sink::captured_output();
+        let timer =
+            time!(crate_logger!() => "slow_op_fields_test").warn_if_gt(std::time::Duration::ZERO);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        timer.end();
+        flush();
+
+        let output = crate::sink::captured_output();
+        let line = output
+            .lines()
+            .find(|line| line.contains("Timer 'slow_op_fields_test' took"))
+            .expect("over limit timer was logged");
+        assert!(line.contains(" op=slow_op_fields_test "), "{line}");
+        assert!(line.contains(" limit_ms=0"), "{line}");
+        let elapsed_ms = line
+            .split(" elapsed_ms=")
+            .nth(1)
+            .and_then(|rest| rest.split(' ').next())
+            .and_then(|elapsed_ms| elapsed_ms.parse::<u64>().ok())
+            .expect("elapsed_ms field");
+        assert!(elapsed_ms >= 2, "{line}");
+        context::with_current(|fields| assert!(fields.is_empty(), "{fields:?}"));
+    }
+
+    #[test]
+    fn test_filter_parse_error_reported_once() {
+        let _lock = filter::REFRESH_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        crate::sink::captured_output();
+        let err = "filter_parse_error_test: invalid level";
+        assert!(report_filter_parse_error(err));
+        assert!(!report_filter_parse_error(err));
+        assert!(report_filter_parse_error(
+            "filter_parse_error_test: other error"
+        ));
+        flush();
+
+        let output = crate::sink::captured_output();
+        assert_eq!(
+            output
+                .lines()
+                .filter(|line| line.contains(err))
+                .inspect(|line| assert!(line.contains("zlog.filter"), "{line}"))
+                .count(),
+            1,
+            "{output}"
+        );
+
+        // Only the most recent errors are remembered.
+        for index in 0..REPORTED_FILTER_ERRORS_MAX {
+            report_filter_parse_error(&format!("filter_parse_error_test: error {index}"));
+        }
+        assert!(REPORTED_FILTER_ERRORS.lock().unwrap().len() <= REPORTED_FILTER_ERRORS_MAX);
+        assert!(report_filter_parse_error(err));
+    }
+
+    #[test]
+    fn test_logger_is_enabled() {
+        let _lock = filter::REFRESH_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        let logger = scoped!("is_enabled_test");
+        filter::refresh_from_settings(&collections::HashMap::default());
+        assert!(logger.is_enabled(log::Level::Error));
+        assert!(!logger.is_enabled(log::Level::Trace));
+
+        filter::mute_scope(&["zlog", "is_enabled_test"]);
+        assert!(filter::is_possibly_enabled_level(log::Level::Error));
+        assert!(!logger.is_enabled(log::Level::Error));
+        assert!(crate_logger!().is_enabled(log::Level::Error));
+        filter::unmute_scope(&["zlog", "is_enabled_test"]);
+        assert!(logger.is_enabled(log::Level::Error));
+    }
+
+    #[test]
+    fn test_would_log() {
+        use log::Log as _;
+
+        let _lock = filter::REFRESH_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        crate::sink::captured_output();
+        let module_path = "would_log_test::module";
+        let levels = [
+            log::Level::Error,
+            log::Level::Warn,
+            log::Level::Info,
+            log::Level::Debug,
+            log::Level::Trace,
+        ];
+        for level in levels {
+            Zlog {}.log(
+                &log::Record::builder()
+                    .args(format_args!("would_log {level}"))
+                    .level(level)
+                    .target(module_path)
+                    .module_path_static(Some(module_path))
+                    .build(),
+            );
+        }
+        flush();
+
+        let output = crate::sink::captured_output();
+        for level in levels {
+            let emitted = output
+                .lines()
+                .any(|line| line.ends_with(&format!("would_log {level}")));
+            assert_eq!(would_log(level, module_path), emitted, "{level}");
+        }
+        assert!(would_log(log::Level::Error, module_path));
+        assert!(!would_log(log::Level::Trace, module_path));
+    }
+
+    #[test]
+    fn test_intern_module_path() {
+        const MODULE_PATH: &str = "zlog::tests::test_intern_module_path::dynamic";
+        let first = intern_module_path(&String::from(MODULE_PATH));
+        let cache_len = MODULE_PATH_CACHE.read().unwrap().len();
+        let second = intern_module_path(&String::from(MODULE_PATH));
+        assert_eq!(first, MODULE_PATH);
+        assert!(std::ptr::eq(first, second));
+        assert_eq!(MODULE_PATH_CACHE.read().unwrap().len(), cache_len);
+        assert!(MODULE_PATH_CACHE.read().unwrap().contains(MODULE_PATH));
+    }
 }
@@ -0,0 +1,73 @@
+//! Configuring the sinks is process wide, so this runs in its own test binary.
+
+use std::borrow::Cow;
+
+use zlog::sink::{Rotation, SinkConfig, SinkTarget, Timezone};
+
+#[test]
+fn test_configure_before_init() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let path = temp_dir.path().join("configured.log");
+    // SAFETY: this is the only test in this binary, so nothing reads the
+    // environment concurrently.
+    unsafe { std::env::set_var("ZED_LOG_OUTPUT", "stdout") };
+
+    assert!(
+        zlog::sink::configure(SinkConfig {
+            timestamp_format: Some(Cow::Borrowed("%Q")),
+            ..SinkConfig::default()
+        })
+        .is_err()
+    );
+    zlog::sink::configure(SinkConfig {
+        target: Some(SinkTarget::File {
+            path: path.clone(),
+            mode: zlog::OpenMode::Truncate,
+        }),
+        timestamp_format: Some(Cow::Borrowed("[configured %Y]")),
+        timezone: Timezone::Utc,
+        format: zlog::sink::OutputFormat::Text,
+        color: zlog::sink::ColorMode::Never,
+        rotation: Some(Rotation {
+            path: temp_dir.path().join("configured.log.old"),
+            compress: false,
+        }),
+        batch_size: Some(4),
+    })
+    .unwrap();
+    assert!(zlog::sink::configure(SinkConfig::default()).is_err());
+
+    zlog::try_init(None).unwrap();
+    assert!(zlog::sink::has_configured_target());
+    assert!(!zlog::has_output_from_env());
+    assert_eq!(zlog::sink::timestamp_timezone(), Timezone::Utc);
+    assert_eq!(zlog::sink::color_mode(), zlog::sink::ColorMode::Never);
+
+    let snapshot = zlog::sink::config_snapshot();
+    assert_eq!(snapshot.targets, ["file"]);
+    assert_eq!(snapshot.file, Some(path.clone()));
+    assert_eq!(
+        snapshot.rotation,
+        Some(Rotation {
+            path: temp_dir.path().join("configured.log.old"),
+            compress: false,
+        })
+    );
+    assert_eq!(snapshot.timestamp_format, "[configured %Y]");
+    assert_eq!(snapshot.timezone, Timezone::Utc);
+    assert_eq!(snapshot.color, zlog::sink::ColorMode::Never);
+    assert_eq!(snapshot.batch_size, 4);
+    let json = serde_json::to_value(&snapshot).unwrap();
+    assert_eq!(json["timezone"], "utc");
+    assert_eq!(json["format"], "text");
+
+    zlog::warn!(zlog::scoped!("configure_test") => "written to the configured file");
+    zlog::flush();
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    let line = contents
+        .lines()
+        .find(|line| line.contains("written to the configured file"))
+        .unwrap_or_else(|| panic!("record missing from {contents:?}"));
+    assert!(line.starts_with("[configured "), "{line}");
+}
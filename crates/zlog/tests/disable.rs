@@ -0,0 +1,70 @@
+//! Disabling logging is process wide, so this runs in its own test binary.
+
+use std::{
+    io::{self, Write},
+    sync::{Arc, Mutex, atomic::Ordering},
+};
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_disable_from_env() {
+    // SAFETY: this is the only test in this binary, so nothing reads the
+    // environment concurrently.
+    unsafe { std::env::set_var("ZED_LOG_DISABLE", "1") };
+    let output = SharedBuffer::default();
+    zlog::init_output_writer(Box::new(output.clone()));
+    zlog::try_init(None).unwrap();
+
+    assert!(zlog::filter::is_disabled());
+    assert_eq!(
+        zlog::filter::LEVEL_ENABLED_MAX_CONFIG.load(Ordering::Acquire),
+        log::LevelFilter::Off as u8
+    );
+    assert_eq!(log::max_level(), log::LevelFilter::Off);
+    assert!(!zlog::filter::is_possibly_enabled_level(log::Level::Error));
+    zlog::with_level(log::LevelFilter::Trace, || {
+        assert!(!zlog::filter::is_possibly_enabled_level(log::Level::Error));
+    });
+
+    log::error!("logged while disabled");
+    zlog::error!(zlog::scoped!("disable_test") => "logged while disabled");
+    zlog::flush();
+    assert!(output.0.lock().unwrap().is_empty());
+
+    unsafe { std::env::remove_var("ZED_LOG_DISABLE") };
+    unsafe { std::env::set_var("ZED_LOG", "off") };
+    zlog::try_init(None).unwrap();
+    assert!(zlog::filter::is_disabled());
+
+    unsafe { std::env::set_var("ZED_LOG", "info,disable=debug") };
+    zlog::try_init(None).unwrap();
+    assert!(!zlog::filter::is_disabled());
+    assert_eq!(log::max_level(), log::LevelFilter::max());
+    log::error!("logged after enabling");
+
+    // Re-enabling restores the configured levels without a refresh.
+    zlog::filter::set_disabled(true);
+    assert!(!zlog::filter::is_possibly_enabled_level(log::Level::Error));
+    zlog::filter::set_disabled(false);
+    assert!(zlog::filter::is_possibly_enabled_level(log::Level::Debug));
+    assert!(!zlog::filter::is_possibly_enabled_level(log::Level::Trace));
+    zlog::debug!(zlog::scoped!("disable_test") => "logged after re-enabling");
+
+    zlog::flush();
+    let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+    assert!(output.contains("logged after enabling"), "{output}");
+    assert!(output.contains("logged after re-enabling"), "{output}");
+    assert!(!output.contains("logged while disabled"), "{output}");
+}
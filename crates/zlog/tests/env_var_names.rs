@@ -0,0 +1,24 @@
+//! The environment variables the filter is read from are process wide, so
+//! this runs in its own test binary.
+
+#[test]
+fn test_custom_env_var_name() {
+    // SAFETY: this is the only test in this binary, so nothing reads the
+    // environment concurrently.
+    unsafe {
+        std::env::remove_var("ZED_LOG");
+        std::env::remove_var("RUST_LOG");
+        std::env::set_var("MYTOOL_LOG", "mytool=trace");
+    }
+    zlog::set_env_var_names(&["MYTOOL_LOG", "ZED_LOG"]);
+    zlog::process_env(None);
+    zlog::filter::refresh_from_settings(&Default::default());
+
+    let scope = ["mytool", "", "", ""];
+    assert!(zlog::filter::is_scope_enabled(
+        &scope,
+        None,
+        log::Level::Trace
+    ));
+    assert!(zlog::filter::to_env_string().contains("mytool=trace"));
+}
@@ -0,0 +1,50 @@
+//! Field filters apply to every record in the process, so this runs in its own
+//! test binary.
+
+use std::{
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_field_filter() {
+    let output = SharedBuffer::default();
+    zlog::init_output_writer(Box::new(output.clone()));
+    zlog::filter::add_field_filter("request_id", "abc");
+
+    {
+        let _request = zlog::context::scoped("request_id", "abc");
+        zlog::warn!(zlog::scoped!("field_filter_test") => "matching record");
+    }
+    {
+        let _request = zlog::context::scoped("request_id", "xyz");
+        zlog::warn!(zlog::scoped!("field_filter_test") => "other request");
+    }
+    zlog::warn!(zlog::scoped!("field_filter_test") => "no request");
+
+    zlog::filter::clear_field_filters();
+    zlog::warn!(zlog::scoped!("field_filter_test") => "after clearing");
+    zlog::flush();
+
+    let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+    assert!(
+        output.contains("matching record request_id=abc"),
+        "{output}"
+    );
+    assert!(!output.contains("other request"), "{output}");
+    assert!(!output.contains("no request"), "{output}");
+    assert!(output.contains("after clearing"), "{output}");
+}
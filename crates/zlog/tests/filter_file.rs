@@ -0,0 +1,64 @@
+//! The filter file is named through the environment, so this runs in its own
+//! test binary.
+
+use log::Level;
+use zlog::filter::is_scope_enabled;
+
+#[test]
+fn test_filter_from_file() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let path = temp_dir.path().join("filter");
+    std::fs::write(
+        &path,
+        "# Global level\n\
+         warn\n\
+         \n\
+         filter_file_test=trace # noisy while debugging\n\
+         filter_file_test.quiet=off,other_filter_file_test=info\n",
+    )
+    .unwrap();
+    // SAFETY: this is the only test in this binary, so nothing reads the
+    // environment concurrently.
+    unsafe { std::env::set_var("ZED_LOG", format!("@{}", path.display())) };
+    zlog::try_init(None).unwrap();
+
+    assert!(is_scope_enabled(
+        &["filter_file_test", "", "", ""],
+        None,
+        Level::Trace
+    ));
+    assert!(!is_scope_enabled(
+        &["filter_file_test", "quiet", "", ""],
+        None,
+        Level::Error
+    ));
+    assert!(is_scope_enabled(
+        &["other_filter_file_test", "", "", ""],
+        None,
+        Level::Info
+    ));
+    assert!(!is_scope_enabled(
+        &["other_filter_file_test", "", "", ""],
+        None,
+        Level::Debug
+    ));
+    assert!(is_scope_enabled(
+        &["unlisted_filter_file_test", "", "", ""],
+        None,
+        Level::Warn
+    ));
+    assert!(!is_scope_enabled(
+        &["unlisted_filter_file_test", "", "", ""],
+        None,
+        Level::Info
+    ));
+
+    unsafe {
+        std::env::set_var(
+            "ZED_LOG",
+            format!("@{}", temp_dir.path().join("missing").display()),
+        )
+    };
+    zlog::try_init(None).unwrap();
+    assert!(!zlog::filter::is_disabled());
+}
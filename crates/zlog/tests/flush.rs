@@ -0,0 +1,41 @@
+//! Installs a writer that fails to flush, which would break the output of any
+//! other test, so this runs in its own test binary.
+
+use std::{
+    io::{self, Write},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+struct FailingFlush(Arc<AtomicBool>);
+
+impl Write for FailingFlush {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.store(true, Ordering::Release);
+        Err(io::Error::other("disk full"))
+    }
+}
+
+#[test]
+fn test_flush_attempts_every_sink() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let path: &'static _ = Box::leak(Box::new(temp_dir.path().join("zed.log")));
+    zlog::init_output_file(path, None, zlog::OpenMode::Truncate).unwrap();
+    let writer_flushed = Arc::new(AtomicBool::new(false));
+    zlog::init_output_writer(Box::new(FailingFlush(writer_flushed.clone())));
+    zlog::sink::set_batch_size(16);
+
+    zlog::warn!(zlog::scoped!("flush_test") => "buffered until flushed");
+    let err = zlog::try_flush().unwrap_err().to_string();
+
+    assert!(writer_flushed.load(Ordering::Acquire));
+    assert_eq!(err, "Failed to flush log sinks: writer: disk full");
+    let contents = std::fs::read_to_string(path).unwrap();
+    assert!(contents.contains("buffered until flushed"), "{contents}");
+}
@@ -0,0 +1,24 @@
+//! Installing the logger is process wide, so this runs in its own test binary.
+
+fn is_enabled(level: log::Level) -> bool {
+    zlog::filter::is_scope_enabled(&["init_test", "", "", ""], None, level)
+}
+
+#[test]
+fn test_init_twice() {
+    // SAFETY: this is the only test in this binary, so nothing reads the
+    // environment concurrently.
+    unsafe { std::env::set_var("ZED_LOG", "warn") };
+    zlog::try_init(None).unwrap();
+    assert!(!is_enabled(log::Level::Info));
+    assert!(is_enabled(log::Level::Warn));
+
+    unsafe { std::env::set_var("ZED_LOG", "init_test=debug") };
+    zlog::try_init(None).unwrap();
+    assert!(is_enabled(log::Level::Debug));
+    assert!(zlog::filter::is_scope_enabled(
+        &["other", "", "", ""],
+        None,
+        log::Level::Info
+    ));
+}
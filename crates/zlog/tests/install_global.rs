@@ -0,0 +1,37 @@
+//! Installing a global `log` logger is process wide and can only be done once,
+//! so this runs in its own test binary.
+
+use std::{
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_install_global() {
+    let output = SharedBuffer::default();
+    zlog::init_output_writer(Box::new(output.clone()));
+    zlog::scoped!("installed").install_global().unwrap();
+    assert!(zlog::scoped!("again").install_global().is_err());
+
+    log::error!("logged through the installed logger");
+    zlog::flush();
+    let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+    let line = output
+        .lines()
+        .find(|line| line.ends_with("logged through the installed logger"))
+        .expect("record is logged through the installed logger");
+    assert!(line.contains(" ERROR [install_global.installed] "), "{line}");
+}
@@ -0,0 +1,55 @@
+//! The writer output is process wide, so this runs in its own test binary.
+
+use std::{
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_process_meta_header() {
+    let meta = zlog::ProcessMeta {
+        pid: std::process::id(),
+        app_version: "1.2.3-dev".to_string(),
+        host: "build-host".to_string(),
+        start_time: chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+    };
+    let output = SharedBuffer::default();
+    zlog::init_output_json_with_meta(Box::new(output.clone()), &meta).unwrap();
+    zlog::try_init(None).unwrap();
+
+    zlog::error!(zlog::scoped!("process_meta_test") => "first record");
+    zlog::error!(zlog::scoped!("process_meta_test") => "second record");
+    zlog::flush();
+
+    let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+    let mut lines = output.lines();
+    let header: serde_json::Value = serde_json::from_str(lines.next().unwrap()).unwrap();
+    assert_eq!(
+        header,
+        serde_json::json!({
+            "pid": std::process::id(),
+            "app_version": "1.2.3-dev",
+            "host": "build-host",
+            "start_time": "2023-11-14T22:13:20Z",
+        })
+    );
+    let records = lines
+        .filter(|line| line.contains("[process_meta.process_meta_test]"))
+        .collect::<Vec<_>>();
+    assert_eq!(records.len(), 2, "{output}");
+    assert!(records[0].ends_with("first record"), "{output}");
+    assert!(records[1].ends_with("second record"), "{output}");
+}
@@ -0,0 +1,30 @@
+//! Resetting the filter clears process-wide overrides, so this runs in its own
+//! test binary.
+
+use collections::HashMap;
+
+#[test]
+fn test_reset() {
+    let scope = ["reset_test", "", "", ""];
+    let sub_scope = ["reset_test", "sub", "", ""];
+    let is_enabled =
+        |scope: &[&'static str; 4], level| zlog::filter::is_scope_enabled(scope, None, level);
+
+    zlog::filter::refresh_from_settings(&HashMap::from_iter([(
+        "reset_test".to_string(),
+        "debug".to_string(),
+    )]));
+    assert!(is_enabled(&scope, log::Level::Debug));
+
+    zlog::filter::mute_scope(&["reset_test", "sub"]);
+    zlog::filter::add_field_filter("request_id", "abc");
+    assert!(!is_enabled(&scope, log::Level::Debug));
+    assert!(!is_enabled(&sub_scope, log::Level::Error));
+
+    zlog::filter::reset();
+    assert!(is_enabled(&scope, log::Level::Debug));
+    assert!(!is_enabled(&scope, log::Level::Trace));
+    assert!(is_enabled(&sub_scope, log::Level::Debug));
+    assert!(zlog::filter::snapshot().muted.is_empty());
+    assert!(zlog::filter::to_env_string().contains("reset_test=debug"));
+}
@@ -0,0 +1,91 @@
+//! Sampling is configured through the environment, so this runs in its own
+//! test binary.
+
+use std::{
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+use log::Level;
+use zlog::filter::{is_sampled, is_scope_enabled};
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_sampling_from_env() {
+    // SAFETY: this is the only test in this binary, so nothing reads the
+    // environment concurrently.
+    unsafe {
+        std::env::set_var(
+            "ZED_LOG",
+            "info,sampled_test=trace@1/10,sampled_test.dense=debug@3/4,sampled_crate::module=debug@1/2,\
+             sampling.guarded=trace@1/2,sampling.timed=trace@1/2",
+        )
+    };
+    let output = SharedBuffer::default();
+    zlog::init_output_writer(Box::new(output.clone()));
+    zlog::try_init(None).unwrap();
+
+    let emitted = |scope: [&'static str; 4], module_path, level| {
+        (0..1000)
+            .filter(|_| {
+                is_scope_enabled(&scope, module_path, level) && is_sampled(&scope, module_path)
+            })
+            .count()
+    };
+    let sampled = ["sampled_test", "sub", "", ""];
+    let dense = ["sampled_test", "dense", "", ""];
+    let unsampled = ["unsampled_test", "", "", ""];
+
+    let count = emitted(sampled, None, Level::Trace);
+    assert!((90..=110).contains(&count), "{count}");
+    let count = emitted(dense, None, Level::Debug);
+    assert!((700..=800).contains(&count), "{count}");
+    assert_eq!(emitted(dense, None, Level::Trace), 0);
+    let count = emitted(
+        ["sampled_crate", "", "", ""],
+        Some("sampled_crate::module::sub"),
+        Level::Debug,
+    );
+    assert!((450..=550).contains(&count), "{count}");
+    assert_eq!(emitted(unsampled, None, Level::Info), 1000);
+    assert_eq!(emitted(unsampled, None, Level::Debug), 0);
+
+    // Checking whether a logger is enabled doesn't count towards the sample,
+    // so guarding a record with the check still emits half of them.
+    let guarded = zlog::scoped!("guarded");
+    for i in 0..100 {
+        assert!(guarded.is_enabled(Level::Info));
+        if guarded.is_enabled(Level::Info) {
+            zlog::info!(guarded => "guarded record {i}");
+        }
+    }
+    // Likewise, timers only count when they log.
+    let timed = zlog::scoped!("timed");
+    for _ in 0..100 {
+        zlog::time!(timed => "sampled timer").end();
+    }
+    zlog::flush();
+
+    let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+    let lines = |needle| output.lines().filter(|line| line.contains(needle)).count();
+    assert_eq!(lines("guarded record "), 50, "{output}");
+    assert_eq!(lines("Timer 'sampled timer' finished in"), 50, "{output}");
+
+    assert!(
+        zlog::filter::to_env_string().contains("sampled_test=trace"),
+        "{}",
+        zlog::filter::to_env_string()
+    );
+}
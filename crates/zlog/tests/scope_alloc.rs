@@ -0,0 +1,74 @@
+//! Counts heap allocations with a global allocator, so this runs in its own
+//! test binary.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        _ = ALLOCATIONS.try_with(|allocations| allocations.set(allocations.get() + 1));
+        // SAFETY: forwarded with the caller's guarantees.
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: forwarded with the caller's guarantees.
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations_during(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn test_scope_alloc_filtering_does_not_allocate() {
+    zlog::filter::refresh_from_settings(
+        &[("alloc_test.enabled".to_string(), "debug".to_string())]
+            .into_iter()
+            .collect(),
+    );
+    let enabled = ["alloc_test", "enabled", "nested", ""].map(str::to_string);
+    let disabled = ["alloc_test", "other", "", ""].map(str::to_string);
+
+    let allocations = allocations_during(|| {
+        for level in [log::Level::Error, log::Level::Debug, log::Level::Trace] {
+            for module_path in [None, Some("alloc_test::module")] {
+                std::hint::black_box(zlog::filter::is_scope_alloc_enabled(
+                    &enabled,
+                    module_path,
+                    level,
+                ));
+                std::hint::black_box(zlog::filter::is_scope_alloc_enabled(
+                    &disabled,
+                    module_path,
+                    level,
+                ));
+            }
+        }
+    });
+    assert_eq!(allocations, 0);
+    assert!(zlog::filter::is_scope_alloc_enabled(
+        &enabled,
+        None,
+        log::Level::Debug
+    ));
+    assert!(!zlog::filter::is_scope_alloc_enabled(
+        &disabled,
+        None,
+        log::Level::Debug
+    ));
+}
@@ -0,0 +1,6 @@
+use zlog::scoped;
+
+fn main() {
+    let logger = scoped!("a");
+    let _logger = scoped!(scoped!(scoped!(scoped!(logger => "b") => "c") => "d") => "e");
+}
@@ -7,13 +7,18 @@ use settings::{RegisterSetting, Settings, SettingsStore};
 pub fn init(cx: &mut App) {
     cx.observe_global::<SettingsStore>(|cx| {
         let zlog_settings = ZlogSettings::get_global(cx);
-        zlog::filter::refresh_from_settings(&zlog_settings.scopes);
+        zlog::filter::refresh_from_settings_with_default_level(
+            Some(&zlog_settings.default_level),
+            &zlog_settings.scopes,
+        );
     })
     .detach();
 }
 
 #[derive(Clone, Debug, RegisterSetting)]
 pub struct ZlogSettings {
+    /// The log level of scopes that aren't configured in `scopes`.
+    pub default_level: String,
     /// A map of log scopes to the desired log level.
     /// Useful for filtering out noisy logs or enabling more verbose logging.
     ///
@@ -24,7 +29,41 @@ pub struct ZlogSettings {
 impl Settings for ZlogSettings {
     fn from_settings(content: &settings::SettingsContent) -> Self {
         ZlogSettings {
+            default_level: content.log_level.clone().unwrap(),
             scopes: content.log.clone().unwrap(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::TestAppContext;
+
+    #[gpui::test]
+    fn test_settings_drive_filter(cx: &mut TestAppContext) {
+        cx.update(|cx| {
+            let settings_store = SettingsStore::test(cx);
+            cx.set_global(settings_store);
+            init(cx);
+        });
+        cx.update(|cx| {
+            SettingsStore::update_global(cx, |store, cx| {
+                store
+                    .set_user_settings(
+                        r#"{"log_level": "trace", "log": {"zlog_settings_test.quiet": "warn"}}"#,
+                        cx,
+                    )
+                    .expect("Failed to set test user settings");
+            });
+        });
+
+        let is_enabled = |scope: &str, level| {
+            zlog::filter::is_scope_enabled(&["zlog_settings_test", scope, "", ""], None, level)
+        };
+        assert!(zlog::filter::is_possibly_enabled_level(log::Level::Trace));
+        assert!(is_enabled("", log::Level::Trace));
+        assert!(is_enabled("quiet", log::Level::Warn));
+        assert!(!is_enabled("quiet", log::Level::Info));
+    }
+}